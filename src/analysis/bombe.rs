@@ -0,0 +1,368 @@
+use crate::analysis::scrambler::{index, letter, parse_rotor_orders, resolve_reflector, rotor_orders, Scrambler,
+                                 SetupError};
+use crate::machine::enigma::{Enigma, EnigmaError};
+use crate::machine::model::Model;
+use std::fmt;
+
+/// Every register of the Bombe live
+const ALL_LIVE: u32 = (1 << 26) - 1;
+
+/// A letter pair of a menu: a crib letter, the ciphertext letter under it, and its
+/// position in the message (counting from 0)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MenuEdge {
+    pub plain: char,
+    pub cipher: char,
+    pub position: usize,
+}
+
+/// Menu of the Bombe, the graph of letter pairs implied by placing a crib under the
+/// ciphertext. Each pair is joined by a scrambler set to the position of the pair in the
+/// message, and the loops of the graph are what lets the Bombe reject wrong settings.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Menu {
+    edges: Vec<MenuEdge>,
+}
+
+impl fmt::Display for Menu {
+    /// Pairs as "<position>:<crib letter><cipher letter>", followed by the loop count
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let edges: Vec<String> = self.edges.iter()
+            .map(|e| format!("{}:{}{}", e.position, e.plain.to_ascii_uppercase(),
+                             e.cipher.to_ascii_uppercase()))
+            .collect();
+        write!(f, "{} ({} loops)", edges.join(" "), self.loops())
+    }
+}
+
+impl Menu {
+    /// Build the menu of a crib placed under the ciphertext starting at the given offset.
+    /// Characters other than letters are ignored in both. Fails if the crib runs past the
+    /// end of the ciphertext, or if a crib letter sits over the same ciphertext letter
+    /// (an Enigma never enciphers a letter to itself).
+    pub fn new(crib: &str, ciphertext: &str, offset: usize) -> Result<Self, BombeError> {
+        let crib: Vec<char> = letters(crib);
+        let ciphertext: Vec<char> = letters(ciphertext);
+        if crib.is_empty() || offset + crib.len() > ciphertext.len() {
+            return Err(BombeError::InvalidMenu(
+                format!("crib of {} letters at {} (ciphertext has {} letters)",
+                        crib.len(), offset, ciphertext.len())));
+        }
+        let mut edges: Vec<MenuEdge> = Vec::new();
+        for (idx, plain) in crib.iter().enumerate() {
+            let cipher = ciphertext[offset + idx];
+            if *plain == cipher {
+                return Err(BombeError::InvalidMenu(
+                    format!("{} at {} would encipher to itself", plain, offset + idx)));
+            }
+            edges.push(MenuEdge {
+                plain: *plain,
+                cipher,
+                position: offset + idx,
+            });
+        }
+        Ok(Self {
+            edges,
+        })
+    }
+
+    /// Letter pairs of the menu, in message order
+    pub fn edges(&self) -> &[MenuEdge] {
+        &self.edges
+    }
+
+    /// Letters of the menu, in alphabetical order
+    pub fn letters(&self) -> Vec<char> {
+        let mut letters: Vec<char> = self.edges.iter().flat_map(|e| [e.plain, e.cipher]).collect();
+        letters.sort();
+        letters.dedup();
+        letters
+    }
+
+    /// Number of independent loops (closed chains of pairs) in the menu
+    pub fn loops(&self) -> usize {
+        // Edges - vertices + connected components, counting components with union find
+        let mut parent: Vec<usize> = (0..26).collect();
+        fn root(parent: &mut [usize], mut letter: usize) -> usize {
+            while parent[letter] != letter {
+                parent[letter] = parent[parent[letter]];
+                letter = parent[letter];
+            }
+            letter
+        }
+        let mut loops = 0;
+        for edge in &self.edges {
+            let a = root(&mut parent, index(edge.plain) as usize);
+            let b = root(&mut parent, index(edge.cipher) as usize);
+            if a == b {
+                loops += 1;
+            } else {
+                parent[a] = b;
+            }
+        }
+        loops
+    }
+
+    /// Letter the Bombe tests hypotheses on, the one with the most pairs
+    pub fn test_letter(&self) -> Option<char> {
+        self.letters().into_iter()
+            .max_by_key(|letter| {
+                let pairs = self.edges.iter().filter(|e| e.plain == *letter || e.cipher == *letter).count();
+                // Prefer the first letter on ties
+                (pairs, std::cmp::Reverse(*letter))
+            })
+    }
+}
+
+/// A position where the Bombe stopped, along with the Stecker pairs it implies
+#[derive(Clone, PartialEq, Debug)]
+pub struct Stop {
+    pub model: Model,
+    pub reflector: String,
+    /// Rotor order from left to right
+    pub rotors: Vec<String>,
+    /// Rotor positions (with ring settings at A) before the first letter of the message
+    pub positions: String,
+    /// Stecker pairs deduced from the menu, in alphabetical order. A letter paired with
+    /// itself is unsteckered.
+    pub steckers: Vec<(char, char)>,
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let steckers: Vec<String> = self.steckers.iter()
+            .map(|(a, b)| format!("{}{}", a.to_ascii_uppercase(), b.to_ascii_uppercase()))
+            .collect();
+        write!(f, "{} {} {}: {}", self.reflector, self.rotors.join("-"),
+               self.positions.to_ascii_uppercase(), steckers.join(" "))
+    }
+}
+
+impl Stop {
+    /// Machine set up with the rotors, positions and Stecker pairs of the stop, with the
+    /// ring settings at A. As the Bombe doesn't know the ring settings, a decrypt with it
+    /// goes wrong from the first turnover of the middle rotor that differs from the true one.
+    pub fn machine(&self) -> Result<Enigma, EnigmaError> {
+        let rings: Vec<&str> = self.rotors.iter().map(|_| "01").collect();
+        let pairs: Vec<String> = self.steckers.iter()
+            .filter(|(a, b)| a != b)
+            .map(|(a, b)| format!("{}{}", a, b).to_ascii_uppercase())
+            .collect();
+        format!("{} {} {} {} {} {}", self.model, self.reflector, self.rotors.join("-"),
+                rings.join("-"), self.positions.to_ascii_uppercase(), pairs.join(" ")).parse()
+    }
+}
+
+/// Simulation of the Turing-Welchman Bombe. For each rotor order and each start position it
+/// connects a scrambler for every pair of the menu, energizes a hypothesis for the Stecker
+/// partner of the test letter, and lets the current spread through the scramblers and the
+/// diagonal board (which connects the wire for "A steckered to B" with "B steckered to A").
+/// A position where some hypothesis stays consistent, with at most one live wire in each
+/// register, is a stop. Like the real Bombe, it assumes the middle rotor doesn't turn over
+/// within the crib and tests positions with the ring settings at A.
+#[derive(Clone, Debug)]
+pub struct Bombe {
+    model: Model,
+    reflector: String,
+    rotor_orders: Vec<Vec<String>>,
+}
+
+impl Bombe {
+    /// Create a Bombe for a three rotor model with a plugboard, which tests every order of
+    /// three different rotors from the model's catalogue with the given reflector
+    pub fn new(model: Model, reflector: &str) -> Result<Self, BombeError> {
+        let name = resolve_reflector(model, reflector)?;
+        Ok(Self {
+            model,
+            reflector: name.to_string(),
+            rotor_orders: rotor_orders(model),
+        })
+    }
+
+    /// Only test the given rotor orders, each given from left to right as "II-V-III"
+    pub fn set_rotor_orders(&mut self, orders: &[&str]) -> Result<(), BombeError> {
+        self.rotor_orders = parse_rotor_orders(self.model, orders)?;
+        Ok(())
+    }
+
+    /// Rotor orders the Bombe tests, from left to right
+    pub fn rotor_orders(&self) -> &[Vec<String>] {
+        &self.rotor_orders
+    }
+
+    /// Run the menu on every rotor order and start position, returning the stops in the
+    /// order they were found
+    pub fn run(&self, menu: &Menu) -> Vec<Stop> {
+        let Some(test_letter) = menu.test_letter() else { return Vec::new(); };
+        let test = index(test_letter);
+        let mut connections: Vec<Vec<(u8, usize)>> = vec![Vec::new(); 26];
+        for (idx, edge) in menu.edges().iter().enumerate() {
+            connections[index(edge.plain) as usize].push((index(edge.cipher), idx));
+            connections[index(edge.cipher) as usize].push((index(edge.plain), idx));
+        }
+        let mut stops: Vec<Stop> = Vec::new();
+        let mut scramblers: Vec<[u8; 26]> = vec![[0; 26]; menu.edges().len()];
+        let mut live: [u32; 26] = [0; 26];
+        let mut queue: Vec<(u8, u8)> = Vec::with_capacity(26 * 26);
+        for order in &self.rotor_orders {
+            let scrambler = Scrambler::new(self.model, order, &self.reflector)
+                .expect("rotor orders and reflector are checked");
+            let [left, middle, right] = &scrambler.rotors;
+            let reflection = &scrambler.reflector;
+            for l in 0..26 {
+                for m in 0..26 {
+                    // Middle rotor, left rotor and reflector, which don't move within the crib
+                    let inner: [u8; 26] = std::array::from_fn(|x| {
+                        let x = left.forward[l][middle.forward[m][x] as usize];
+                        middle.reverse[m][left.reverse[l][reflection[x as usize] as usize] as usize]
+                    });
+                    for r in 0..26 {
+                        for (edge_scrambler, edge) in scramblers.iter_mut().zip(menu.edges()) {
+                            // The right rotor steps before each letter is enciphered
+                            let position = (r + edge.position + 1) % 26;
+                            *edge_scrambler = std::array::from_fn(|x| {
+                                let x = inner[right.forward[position][scrambler.entry_forward[x] as usize] as usize];
+                                scrambler.entry_reverse[right.reverse[position][x as usize] as usize]
+                            });
+                        }
+                        let steckers = test_hypotheses(test, &connections, &scramblers, &mut live,
+                                                       &mut queue);
+                        for steckers in steckers {
+                            stops.push(Stop {
+                                model: self.model,
+                                reflector: self.reflector.clone(),
+                                rotors: order.clone(),
+                                positions: [l, m, r].iter().map(|p| letter(*p as u8)).collect(),
+                                steckers,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        stops
+    }
+}
+
+/// Try every hypothesis for the Stecker partner of the test letter at one position, returning
+/// the Stecker pairs of each consistent one
+fn test_hypotheses(test: u8, connections: &[Vec<(u8, usize)>], scramblers: &[[u8; 26]],
+                   live: &mut [u32; 26], queue: &mut Vec<(u8, u8)>) -> Vec<Vec<(char, char)>> {
+    let mut consistent: Vec<Vec<(char, char)>> = Vec::new();
+    let mut untested: u32 = ALL_LIVE;
+    while untested != 0 {
+        let hypothesis = untested.trailing_zeros() as u8;
+        energize(test, hypothesis, connections, scramblers, live, queue);
+        untested &= !live[test as usize];
+        if live[test as usize].count_ones() == 1 && live.iter().all(|wires| wires.count_ones() <= 1) {
+            let steckers: Vec<(char, char)> = (0..26u8)
+                .filter(|register| live[*register as usize] != 0)
+                .map(|register| (register, live[register as usize].trailing_zeros() as u8))
+                .filter(|(register, partner)| register <= partner)
+                .map(|(register, partner)| (letter(register), letter(partner)))
+                .collect();
+            consistent.push(steckers);
+        }
+    }
+    consistent
+}
+
+/// Spread the current from the wire of a hypothesis for the test letter, leaving the live
+/// wires of each register as bits. Stops early once every wire of the test register is live.
+fn energize(test: u8, hypothesis: u8, connections: &[Vec<(u8, usize)>], scramblers: &[[u8; 26]],
+            live: &mut [u32; 26], queue: &mut Vec<(u8, u8)>) {
+    *live = [0; 26];
+    queue.clear();
+    queue.push((test, hypothesis));
+    live[test as usize] |= 1 << hypothesis;
+    while let Some((register, wire)) = queue.pop() {
+        let through_scramblers = connections[register as usize].iter()
+            .map(|(other, edge)| (*other, scramblers[*edge][wire as usize]));
+        // The diagonal board
+        let diagonal = std::iter::once((wire, register));
+        for (register, wire) in through_scramblers.chain(diagonal) {
+            if live[register as usize] & (1 << wire) == 0 {
+                live[register as usize] |= 1 << wire;
+                queue.push((register, wire));
+            }
+        }
+        if live[test as usize] == ALL_LIVE {
+            return;
+        }
+    }
+}
+
+/// Letters of a text in lowercase, dropping everything else
+fn letters(text: &str) -> Vec<char> {
+    text.chars().filter(|c| c.is_ascii_alphabetic()).map(|c| c.to_ascii_lowercase()).collect()
+}
+
+pub enum BombeError {
+    InvalidModel(String),
+    InvalidRotorOrder(String),
+    InvalidMenu(String),
+    Enigma(EnigmaError),
+}
+
+impl fmt::Display for BombeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BombeError::InvalidModel(s) => { write!(f, "Invalid Model: {}", s) }
+            BombeError::InvalidRotorOrder(s) => { write!(f, "Invalid Rotor Order: {}", s) }
+            BombeError::InvalidMenu(s) => { write!(f, "Invalid Menu: {}", s) }
+            BombeError::Enigma(err) => { write!(f, "{}", err) }
+        }
+    }
+}
+
+impl From<SetupError> for BombeError {
+    fn from(err: SetupError) -> Self {
+        match err {
+            SetupError::InvalidModel(s) => BombeError::InvalidModel(s),
+            SetupError::InvalidRotorOrder(s) => BombeError::InvalidRotorOrder(s),
+            SetupError::Enigma(err) => BombeError::Enigma(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_bombe {
+    use super::*;
+
+    #[test]
+    fn test_menu() {
+        let menu = Menu::new("wetter", "xx qrwbmz", 2).ok().unwrap();
+        assert_eq!("2:WQ 3:ER 4:TW 5:TB 6:EM 7:RZ (0 loops)", menu.to_string());
+        assert_eq!(Some('e'), menu.test_letter());
+        assert_eq!(vec!['b', 'e', 'm', 'q', 'r', 't', 'w', 'z'], menu.letters());
+        // A and B are joined twice, and A, C and D make a triangle
+        let menu = Menu::new("abacd", "babda", 0).ok().unwrap();
+        assert_eq!(2, menu.loops());
+        assert_eq!("Invalid Menu: e at 1 would encipher to itself",
+                   Menu::new("wetter", "qerzzz", 0).err().unwrap().to_string());
+        assert!(Menu::new("wetter", "qrz", 0).is_err());
+    }
+
+    #[test]
+    fn test_stops() {
+        let mut machine: Enigma = "B II-V-III 01-01-01 KDW AQ EP TL RX HU WC".parse().ok().unwrap();
+        let crib = "wetterberichtbiskaya";
+        let ciphertext = machine.translate(&format!("xx{}", crib));
+        let menu = Menu::new(crib, &ciphertext, 2).ok().unwrap();
+        assert_eq!(2, menu.loops());
+        let mut bombe = Bombe::new(Model::EnigmaI, "b").ok().unwrap();
+        assert_eq!(8 * 7 * 6, bombe.rotor_orders().len());
+        assert!(bombe.set_rotor_orders(&["II-V-IX"]).is_err());
+        assert!(bombe.set_rotor_orders(&["II-V-III"]).is_ok());
+        let stops = bombe.run(&menu);
+        // The only stop is the true position, with the Stecker pairs reached from the test letter
+        assert_eq!(vec!["B II-V-III KDW: AQ BB CW EP GG HU II KK LT OO RX YY ZZ".to_string()],
+                   stops.iter().map(|stop| stop.to_string()).collect::<Vec<String>>());
+        let stop = &stops[0];
+        // The stop deciphers the crib again
+        let mut found = stop.machine().ok().unwrap();
+        assert!(found.translate(&ciphertext).ends_with("biskaya"));
+        assert!(Bombe::new(Model::M4, "b-thin").is_err());
+        assert!(Bombe::new(Model::EnigmaI, "d").is_err());
+    }
+}
@@ -0,0 +1,362 @@
+use crate::analysis::ngram::{Language, Ngrams};
+use crate::analysis::scrambler::{climb_plugboard, letter, letter_indices, parse_rotor_orders, resolve_reflector,
+                                 rotor_orders, Scrambler, SetupError, EMPTY_PLUGBOARD, MAX_WIRES};
+use crate::machine::enigma::{Enigma, EnigmaError};
+use crate::machine::model::Model;
+use std::fmt;
+use std::thread;
+
+/// Fewest ciphertext letters the index of coincidence can tell anything from
+const MIN_LETTERS: usize = 50;
+
+/// A machine setting found by the attack, with the score of its decrypt
+#[derive(Clone, Copy, Debug)]
+struct Setting {
+    /// Index of the rotor order in the orders being tested
+    order: usize,
+    positions: [u8; 3],
+    rings: [u8; 3],
+    plugboard: [u8; 26],
+    score: f64,
+}
+
+/// A candidate key found by the attack, along with its decrypt
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    /// Machine set up for the start of the message
+    pub machine: Enigma,
+    /// Mean log10 probability of the trigrams of the decrypt, higher is better
+    pub score: f64,
+    pub plaintext: String,
+}
+
+impl fmt::Display for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.3} {}", self.score, self.machine)
+    }
+}
+
+/// Ciphertext-only attack on a three rotor Enigma after James Gillogly ("Ciphertext-only
+/// Cryptanalysis of Enigma", Cryptologia 1995). Every rotor order and start position is tried
+/// with the rings at A and an empty plugboard, keeping the settings whose decrypts have the
+/// highest index of coincidence. For each of those survivors the ring settings of the right and middle
+/// rotors are refined, then the plugboard is hill climbed, first by index of coincidence and
+/// then by trigram score in the language of the message (German unless set), and the best of
+/// them are returned as candidates. The rotor orders are shared between threads.
+///
+/// The index of coincidence only stands out through a plugboard with few wires: a few hundred
+/// letters do for half a dozen wires, ten wires want well over a thousand.
+#[derive(Clone, Debug)]
+pub struct Attack {
+    model: Model,
+    reflector: String,
+    rotor_orders: Vec<Vec<String>>,
+    threads: usize,
+    survivors: usize,
+    candidates: usize,
+    max_wires: usize,
+    language: Language,
+}
+
+impl Attack {
+    /// Create an attack on a three rotor model with a plugboard, which tries every order of
+    /// three different rotors from the model's catalogue with the given reflector
+    pub fn new(model: Model, reflector: &str) -> Result<Self, AttackError> {
+        let name = resolve_reflector(model, reflector)?;
+        Ok(Self {
+            model,
+            reflector: name.to_string(),
+            rotor_orders: rotor_orders(model),
+            threads: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            survivors: 300,
+            candidates: 10,
+            max_wires: 10,
+            language: Language::German,
+        })
+    }
+
+    /// Only try the given rotor orders, each given from left to right as "II-V-III"
+    pub fn set_rotor_orders(&mut self, orders: &[&str]) -> Result<(), AttackError> {
+        self.rotor_orders = parse_rotor_orders(self.model, orders)?;
+        Ok(())
+    }
+
+    /// Rotor orders the attack tries, from left to right
+    pub fn rotor_orders(&self) -> &[Vec<String>] {
+        &self.rotor_orders
+    }
+
+    /// Number of threads to search with (at least one), by default one per processor
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// Number of settings kept from the search of the start positions to refine, the right
+    /// setting rarely has the very highest index of coincidence before its rings and plugboard
+    /// are found (at least one, default 300)
+    pub fn set_survivors(&mut self, survivors: usize) {
+        self.survivors = survivors.max(1);
+    }
+
+    /// Number of refined settings to return as candidates (at least one, default 10)
+    pub fn set_candidates(&mut self, candidates: usize) {
+        self.candidates = candidates.max(1);
+    }
+
+    /// Most wires the plugboard is allowed to have (default 10)
+    pub fn set_max_wires(&mut self, max_wires: usize) -> Result<(), AttackError> {
+        if max_wires > MAX_WIRES {
+            return Err(AttackError::InvalidMaxWires(format!("{} (at most {})", max_wires, MAX_WIRES)));
+        }
+        self.max_wires = max_wires;
+        Ok(())
+    }
+
+    /// Language the plaintext is expected in (default German)
+    pub fn set_language(&mut self, language: Language) {
+        self.language = language;
+    }
+
+    /// Run the attack on a ciphertext (characters other than letters are ignored), returning
+    /// the candidates from best to worst
+    pub fn run(&self, ciphertext: &str) -> Result<Vec<Candidate>, AttackError> {
+        let ciphertext = letter_indices(ciphertext);
+        if ciphertext.len() < MIN_LETTERS {
+            return Err(AttackError::InvalidCiphertext(
+                format!("{} letters (at least {} are needed)", ciphertext.len(), MIN_LETTERS)));
+        }
+        let threads = self.threads.min(self.rotor_orders.len()).max(1);
+        // Rotor orders and start positions, each thread taking every n-th rotor order
+        let mut settings: Vec<Setting> = Vec::new();
+        thread::scope(|scope| {
+            let searches: Vec<_> = (0..threads)
+                .map(|first| {
+                    let ciphertext = &ciphertext;
+                    scope.spawn(move || self.search_positions(ciphertext, first, threads))
+                })
+                .collect();
+            for search in searches {
+                for setting in search.join().expect("search thread panicked") {
+                    keep_best(&mut settings, setting, self.survivors());
+                }
+            }
+        });
+        // Rings and plugboard of each of the best settings, again spread over the threads
+        let trigrams = Ngrams::builtin(self.language, 3).ok().expect("built in trigram table");
+        let mut refined: Vec<Setting> = Vec::new();
+        thread::scope(|scope| {
+            let refinements: Vec<_> = settings.chunks(settings.len().div_ceil(threads).max(1))
+                .map(|chunk| {
+                    let ciphertext = &ciphertext;
+                    scope.spawn(move || chunk.iter().map(|s| self.refine(ciphertext, *s, trigrams))
+                        .collect::<Vec<Setting>>())
+                })
+                .collect();
+            for refinement in refinements {
+                refined.extend(refinement.join().expect("refinement thread panicked"));
+            }
+        });
+        refined.sort_by(|a, b| b.score.total_cmp(&a.score));
+        refined.truncate(self.candidates);
+        refined.iter().map(|setting| self.candidate(&ciphertext, setting)).collect()
+    }
+
+    /// Best settings by index of coincidence for every n-th rotor order from the first, with the
+    /// rings at A and no plugboard
+    fn search_positions(&self, ciphertext: &[u8], first: usize, step: usize) -> Vec<Setting> {
+        let mut best: Vec<Setting> = Vec::new();
+        let mut output: Vec<u8> = Vec::with_capacity(ciphertext.len());
+        for order in (first..self.rotor_orders.len()).step_by(step) {
+            let scrambler = self.scrambler(order);
+            for position in 0..26 * 26 * 26 {
+                let positions = [(position / 676) as u8, (position / 26 % 26) as u8, (position % 26) as u8];
+                scrambler.translate(ciphertext, positions, [0; 3], &EMPTY_PLUGBOARD, &mut output);
+                let setting = Setting {
+                    order,
+                    positions,
+                    rings: [0; 3],
+                    plugboard: EMPTY_PLUGBOARD,
+                    score: index_of_coincidence(&output),
+                };
+                keep_best(&mut best, setting, self.survivors());
+            }
+        }
+        best
+    }
+
+    /// Refine the ring settings of the right and middle rotors, moving the rotor along with
+    /// its ring so the wiring stays where it was, then climb the plugboard
+    fn refine(&self, ciphertext: &[u8], setting: Setting, trigrams: &Ngrams) -> Setting {
+        let scrambler = self.scrambler(setting.order);
+        let mut output: Vec<u8> = Vec::with_capacity(ciphertext.len());
+        let mut best = setting;
+        for slot in [2, 1] {
+            let start = best;
+            for ring in 0..26u8 {
+                let mut candidate = start;
+                candidate.rings[slot] = ring;
+                candidate.positions[slot] = (start.positions[slot] + 26 + ring - start.rings[slot]) % 26;
+                scrambler.translate(ciphertext, candidate.positions, candidate.rings, &candidate.plugboard,
+                                    &mut output);
+                candidate.score = index_of_coincidence(&output);
+                if candidate.score > best.score {
+                    best = candidate;
+                }
+            }
+        }
+        let decrypt = |plugboard: &[u8; 26], output: &mut Vec<u8>| {
+            scrambler.translate(ciphertext, best.positions, best.rings, plugboard, output)
+        };
+        let (plugboard, _) = climb_plugboard(best.plugboard, &[false; 26], self.max_wires, decrypt,
+                                             index_of_coincidence);
+        let (plugboard, score) = climb_plugboard(plugboard, &[false; 26], self.max_wires, decrypt,
+                                                 |letters| trigrams.score(letters));
+        Setting { plugboard, score, ..best }
+    }
+
+    /// Number of settings to refine, never fewer than the candidates asked for
+    fn survivors(&self) -> usize {
+        self.survivors.max(self.candidates)
+    }
+
+    fn scrambler(&self, order: usize) -> Scrambler {
+        Scrambler::new(self.model, &self.rotor_orders[order], &self.reflector)
+            .expect("model, rotor orders and reflector are checked")
+    }
+
+    /// Machine and decrypt of a setting
+    fn candidate(&self, ciphertext: &[u8], setting: &Setting) -> Result<Candidate, AttackError> {
+        let rings: Vec<String> = setting.rings.iter().map(|ring| format!("{:02}", ring + 1)).collect();
+        let positions: String = setting.positions.iter().map(|p| letter(*p).to_ascii_uppercase()).collect();
+        let pairs: Vec<String> = (0..26u8)
+            .filter(|x| setting.plugboard[*x as usize] > *x)
+            .map(|x| format!("{}{}", letter(x), letter(setting.plugboard[x as usize])).to_ascii_uppercase())
+            .collect();
+        let machine: Enigma = format!("{} {} {} {} {} {}", self.model, self.reflector,
+                                      self.rotor_orders[setting.order].join("-"), rings.join("-"),
+                                      positions, pairs.join(" ")).parse()?;
+        let mut output: Vec<u8> = Vec::with_capacity(ciphertext.len());
+        self.scrambler(setting.order).translate(ciphertext, setting.positions, setting.rings,
+                                                &setting.plugboard, &mut output);
+        Ok(Candidate {
+            machine,
+            score: setting.score,
+            plaintext: output.into_iter().map(letter).collect(),
+        })
+    }
+}
+
+/// Keep a setting in a list of the best settings (best first) if it is good enough
+fn keep_best(best: &mut Vec<Setting>, setting: Setting, count: usize) {
+    if best.len() == count && best.last().is_some_and(|worst| worst.score >= setting.score) {
+        return;
+    }
+    let at = best.partition_point(|other| other.score >= setting.score);
+    best.insert(at, setting);
+    best.truncate(count);
+}
+
+/// Index of coincidence of letters (numbers 0..26), the chance that two letters picked at
+/// random are the same. Around 0.038 for random letters and 0.076 for German.
+fn index_of_coincidence(letters: &[u8]) -> f64 {
+    let mut counts: [u32; 26] = [0; 26];
+    for l in letters {
+        counts[*l as usize] += 1;
+    }
+    let n = letters.len() as f64;
+    let pairs: f64 = counts.iter().map(|c| (*c as f64) * (*c as f64 - 1.0)).sum();
+    pairs / (n * (n - 1.0))
+}
+
+pub enum AttackError {
+    InvalidModel(String),
+    InvalidRotorOrder(String),
+    InvalidMaxWires(String),
+    InvalidCiphertext(String),
+    Enigma(EnigmaError),
+}
+
+impl fmt::Display for AttackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AttackError::InvalidModel(s) => { write!(f, "Invalid Model: {}", s) }
+            AttackError::InvalidRotorOrder(s) => { write!(f, "Invalid Rotor Order: {}", s) }
+            AttackError::InvalidMaxWires(s) => { write!(f, "Invalid Max Wires: {}", s) }
+            AttackError::InvalidCiphertext(s) => { write!(f, "Invalid Ciphertext: {}", s) }
+            AttackError::Enigma(err) => { write!(f, "{}", err) }
+        }
+    }
+}
+
+impl From<SetupError> for AttackError {
+    fn from(err: SetupError) -> Self {
+        match err {
+            SetupError::InvalidModel(s) => AttackError::InvalidModel(s),
+            SetupError::InvalidRotorOrder(s) => AttackError::InvalidRotorOrder(s),
+            SetupError::Enigma(err) => AttackError::Enigma(err),
+        }
+    }
+}
+
+impl From<EnigmaError> for AttackError {
+    fn from(err: EnigmaError) -> Self {
+        AttackError::Enigma(err)
+    }
+}
+
+#[cfg(test)]
+mod test_ciphertext_only {
+    use super::*;
+
+    const PLAINTEXT: &str = "anoberkommandoderwehrmachtxmeldungvomfuenftenmaixeigenetruppenhabendenraum\
+        umdiestadtgeraeumtxstarkerfeinddruckvonnordenxmunitionundverpflegungreichennurnochfuerzwei\
+        tagexerbittendringendnachschubxwetterlagexregenundniedrigewolkenxstrassenschlammigundnur\
+        bedingtbefahrbarxfeindlicheflugzeugeueberdemabschnittxverlusteheutezweitotexsiebenverwundet\
+        xnaechstemeldungzwoelfuhrxkommandeurderdrittendivision";
+
+    #[test]
+    fn test_attack() {
+        let mut machine: Enigma = "B IV-II-V 01-01-07 HKQ AO BV DS EX FT HZ".parse().ok().unwrap();
+        let ciphertext = machine.translate(PLAINTEXT);
+        let mut attack = Attack::new(Model::EnigmaI, "B").ok().unwrap();
+        assert_eq!(336, attack.rotor_orders().len());
+        assert!(attack.set_rotor_orders(&["IV-II-IX"]).is_err());
+        assert!(attack.set_rotor_orders(&["I-II-III", "IV-II-V"]).is_ok());
+        assert!(attack.set_max_wires(14).is_err());
+        attack.set_threads(2);
+        attack.set_survivors(4);
+        attack.set_candidates(4);
+        let candidates = attack.run(&ciphertext).ok().unwrap();
+        assert_eq!(4, candidates.len());
+        assert!(candidates.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        let best = &candidates[0];
+        assert_eq!(PLAINTEXT, best.plaintext);
+        assert_eq!(vec!["IV", "II", "V"], best.machine.rotor_names());
+        assert_eq!(PLAINTEXT, best.machine.clone().translate(&ciphertext));
+    }
+
+    #[test]
+    fn test_survivors() {
+        let mut machine: Enigma = "B IV-II-V 01-01-07 HKQ AO BV DS EX FT HZ".parse().ok().unwrap();
+        let ciphertext = machine.translate(PLAINTEXT);
+        let mut attack = Attack::new(Model::EnigmaI, "B").ok().unwrap();
+        assert!(attack.set_rotor_orders(&["I-II-III", "V-I-IV", "IV-II-V", "III-V-II", "II-IV-I"]).is_ok());
+        attack.set_threads(2);
+        attack.set_survivors(12);
+        attack.set_candidates(2);
+        let candidates = attack.run(&ciphertext).ok().unwrap();
+        assert_eq!(2, candidates.len());
+        assert_eq!(PLAINTEXT, candidates[0].plaintext);
+        assert_eq!(vec!["IV", "II", "V"], candidates[0].machine.rotor_names());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(Attack::new(Model::M4, "B-thin").is_err());
+        assert!(Attack::new(Model::EnigmaI, "X").is_err());
+        let attack = Attack::new(Model::EnigmaI, "B").ok().unwrap();
+        assert_eq!("Invalid Ciphertext: 5 letters (at least 50 are needed)",
+                   attack.run("abcde").err().unwrap().to_string());
+        assert!((index_of_coincidence(&letter_indices(PLAINTEXT)) - 0.076).abs() < 0.015);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::analysis::bombe::Menu;
+use std::fmt;
+
+/// An admissible place for a crib in a ciphertext, with the menu it gives
+#[derive(Clone, PartialEq, Debug)]
+pub struct Placement {
+    /// Offset of the first crib letter in the ciphertext (counting letters from 0)
+    pub offset: usize,
+    pub menu: Menu,
+}
+
+impl fmt::Display for Placement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:4}: {}", self.offset, self.menu)
+    }
+}
+
+impl Placement {
+    /// Loops in the menu of the placement, the more the better for the Bombe
+    pub fn loops(&self) -> usize {
+        self.menu.loops()
+    }
+}
+
+/// Slide a crib along a ciphertext and list every offset where no crib letter sits over the
+/// same ciphertext letter, as an Enigma never enciphers a letter to itself. Characters other
+/// than letters are ignored in both.
+pub fn placements(crib: &str, ciphertext: &str) -> Vec<Placement> {
+    let crib_length = crib.chars().filter(|c| c.is_ascii_alphabetic()).count();
+    let cipher_length = ciphertext.chars().filter(|c| c.is_ascii_alphabetic()).count();
+    if crib_length == 0 || crib_length > cipher_length {
+        return Vec::new();
+    }
+    (0..=cipher_length - crib_length)
+        .filter_map(|offset| Menu::new(crib, ciphertext, offset).ok()
+            .map(|menu| Placement {
+                offset,
+                menu,
+            }))
+        .collect()
+}
+
+#[cfg(test)]
+mod test_crib {
+    use super::*;
+
+    #[test]
+    fn test_placements() {
+        let offsets: Vec<usize> = placements("abc", "xaxbxcab").iter().map(|p| p.offset).collect();
+        // A crashes at 1, B at 2 and C at 3
+        assert_eq!(vec![0, 4, 5], offsets);
+        let found = placements("abac", "bcca-b");
+        assert_eq!(1, found.len());
+        assert_eq!("   0: 0:AB 1:BC 2:AC 3:CA (2 loops)", found[0].to_string());
+        assert_eq!(2, found[0].loops());
+        assert!(placements("longer crib", "short").is_empty());
+    }
+}
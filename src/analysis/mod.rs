@@ -0,0 +1,6 @@
+pub mod bombe;
+pub mod ciphertext_only;
+pub mod crib;
+pub mod ngram;
+mod scrambler;
+pub mod stecker;
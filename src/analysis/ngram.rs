@@ -0,0 +1,246 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Counts of the n-grams of the built in tables, one "<NGRAM> <count>" line each
+const GERMAN_UNIGRAMS: &str = include_str!("ngrams/german_unigrams.txt");
+const GERMAN_BIGRAMS: &str = include_str!("ngrams/german_bigrams.txt");
+const GERMAN_TRIGRAMS: &str = include_str!("ngrams/german_trigrams.txt");
+const GERMAN_QUADGRAMS: &str = include_str!("ngrams/german_quadgrams.txt");
+const ENGLISH_UNIGRAMS: &str = include_str!("ngrams/english_unigrams.txt");
+const ENGLISH_BIGRAMS: &str = include_str!("ngrams/english_bigrams.txt");
+const ENGLISH_TRIGRAMS: &str = include_str!("ngrams/english_trigrams.txt");
+const ENGLISH_QUADGRAMS: &str = include_str!("ngrams/english_quadgrams.txt");
+
+/// Longest n-grams a table can hold (a table has 26^n entries)
+const MAX_LENGTH: usize = 5;
+
+/// Log10 probabilities of the n-grams of a language, indexed by the letters of the n-gram
+/// as a number in base 26. Besides the built in tables, a table can be parsed from a counts
+/// file of "<NGRAM> <count>" lines, where blank lines and lines starting with '#' are skipped.
+#[derive(Clone, Debug)]
+pub struct Ngrams {
+    length: usize,
+    scores: Vec<f32>,
+}
+
+impl Ngrams {
+    /// Build the table from "<NGRAM> <count>" lines, n-grams which never appear get the
+    /// probability of a hundredth of an occurrence
+    fn from_counts(length: usize, counts: &str) -> Self {
+        let entries: Vec<(usize, f64)> = counts.lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once(' '))
+            .map(|(ngram, count)| (ngram_index(ngram.as_bytes()),
+                                   count.trim().parse::<f64>().unwrap_or_default()))
+            .collect();
+        let total: f64 = entries.iter().map(|(_, count)| count).sum();
+        let mut scores = vec![(0.01 / total).log10() as f32; 26usize.pow(length as u32)];
+        for (idx, count) in entries {
+            scores[idx] = (count / total).log10() as f32;
+        }
+        Self {
+            length,
+            scores,
+        }
+    }
+
+    /// Mean log10 probability of the n-grams of the letters (numbers 0..26)
+    pub(crate) fn score(&self, letters: &[u8]) -> f64 {
+        if letters.len() < self.length {
+            return f64::NEG_INFINITY;
+        }
+        let total: f64 = letters.windows(self.length)
+            .map(|ngram| self.scores[ngram.iter().fold(0, |idx, l| idx * 26 + *l as usize)] as f64)
+            .sum();
+        total / (letters.len() - self.length + 1) as f64
+    }
+
+    /// Built in table of a language for n-grams of 1 to 4 letters, built the first time it
+    /// is needed
+    pub fn builtin(language: Language, length: usize) -> Result<&'static Ngrams, NgramError> {
+        static TABLES: [OnceLock<Ngrams>; 8] = [const { OnceLock::new() }; 8];
+        let counts = match (language, length) {
+            (Language::German, 1) => GERMAN_UNIGRAMS,
+            (Language::German, 2) => GERMAN_BIGRAMS,
+            (Language::German, 3) => GERMAN_TRIGRAMS,
+            (Language::German, 4) => GERMAN_QUADGRAMS,
+            (Language::English, 1) => ENGLISH_UNIGRAMS,
+            (Language::English, 2) => ENGLISH_BIGRAMS,
+            (Language::English, 3) => ENGLISH_TRIGRAMS,
+            (Language::English, 4) => ENGLISH_QUADGRAMS,
+            _ => {
+                return Err(NgramError::InvalidLength(
+                    format!("{} (built in tables have 1 to 4 letters)", length)));
+            }
+        };
+        let table = &TABLES[language as usize * 4 + length - 1];
+        Ok(table.get_or_init(|| Ngrams::from_counts(length, counts)))
+    }
+
+    /// Number of letters in each n-gram of the table
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// Log10 probability of an n-gram, or None if it isn't made of as many letters as the
+    /// table's n-grams
+    pub fn log_probability(&self, ngram: &str) -> Option<f64> {
+        if ngram.len() != self.length || !ngram.bytes().all(|b| b.is_ascii_alphabetic()) {
+            return None;
+        }
+        Some(self.scores[ngram_index(ngram.as_bytes())] as f64)
+    }
+
+    /// Mean log10 probability of the n-grams of a text such as a decrypt, higher is more like
+    /// the language. Characters other than letters are ignored, a text too short to hold an
+    /// n-gram scores negative infinity.
+    pub fn score_text(&self, text: &str) -> f64 {
+        let letters: Vec<u8> = text.bytes().filter(|b| b.is_ascii_alphabetic())
+            .map(|b| b.to_ascii_lowercase() - b'a').collect();
+        self.score(&letters)
+    }
+}
+
+impl FromStr for Ngrams {
+    type Err = NgramError;
+
+    /// Parse a counts file, checking every line before building the table
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut length = 0;
+        let mut total = 0.0;
+        for (idx, line) in s.lines().enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || NgramError::InvalidTable(format!("{:?} on line {}", line, idx + 1));
+            let (ngram, count) = line.split_once(' ').ok_or_else(invalid)?;
+            let count: f64 = count.trim().parse().map_err(|_| invalid())?;
+            if ngram.is_empty() || !ngram.bytes().all(|b| b.is_ascii_alphabetic())
+                || !count.is_finite() || count <= 0.0 {
+                return Err(invalid());
+            }
+            if length == 0 {
+                if ngram.len() > MAX_LENGTH {
+                    return Err(NgramError::InvalidLength(
+                        format!("{} (at most {} letters)", ngram.len(), MAX_LENGTH)));
+                }
+                length = ngram.len();
+            } else if ngram.len() != length {
+                return Err(NgramError::InvalidTable(
+                    format!("{:?} on line {} (expected {} letters)", ngram, idx + 1, length)));
+            }
+            total += count;
+        }
+        if total == 0.0 {
+            return Err(NgramError::InvalidTable("no n-grams counted".to_string()));
+        }
+        Ok(Ngrams::from_counts(length, s))
+    }
+}
+
+/// Index of an n-gram given in letters
+fn ngram_index(ngram: &[u8]) -> usize {
+    ngram.iter().fold(0, |idx, l| idx * 26 + (l.to_ascii_lowercase() - b'a') as usize)
+}
+
+/// Languages with built in n-gram tables
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Language {
+    #[default]
+    German,
+    English,
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Language::German => { write!(f, "German") }
+            Language::English => { write!(f, "English") }
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = NgramError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "german" | "deutsch" | "de" => Ok(Language::German),
+            "english" | "en" => Ok(Language::English),
+            _ => Err(NgramError::InvalidLanguage(s.to_string())),
+        }
+    }
+}
+
+pub enum NgramError {
+    InvalidLanguage(String),
+    InvalidLength(String),
+    InvalidTable(String),
+}
+
+impl fmt::Display for NgramError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NgramError::InvalidLanguage(s) => { write!(f, "Invalid Language: {}", s) }
+            NgramError::InvalidLength(s) => { write!(f, "Invalid N-gram Length: {}", s) }
+            NgramError::InvalidTable(s) => { write!(f, "Invalid N-gram Table: {}", s) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_ngram {
+    use super::*;
+    use crate::analysis::scrambler::letter_indices;
+    use crate::machine::enigma::Enigma;
+
+    #[test]
+    fn test_builtin() {
+        for length in 1..=4 {
+            let german = Ngrams::builtin(Language::German, length).ok().unwrap();
+            let english = Ngrams::builtin(Language::English, length).ok().unwrap();
+            assert_eq!(length, german.length());
+            let deutsch = "Die Wettervorhersage fuer den naechsten Tag";
+            let weather = "The weather forecast for the next day";
+            assert!(german.score_text(deutsch) > english.score_text(deutsch));
+            assert!(english.score_text(weather) > german.score_text(weather));
+        }
+        let trigrams = Ngrams::builtin(Language::German, 3).ok().unwrap();
+        let german = trigrams.score(&letter_indices("diewettervorhersagefuerdennaechstentag"));
+        let random = trigrams.score(&letter_indices("qxjvbzkwpqmxyrtvbnzqjxwkfpvmqzxbyjkd"));
+        assert!(german > random + 1.0);
+        assert_eq!(f64::NEG_INFINITY, trigrams.score(&letter_indices("ab")));
+        assert!(trigrams.log_probability("EIN").unwrap() > trigrams.log_probability("qxj").unwrap());
+        assert_eq!(None, trigrams.log_probability("ei"));
+        assert_eq!(f64::NEG_INFINITY, trigrams.score_text("a b"));
+        assert!(Ngrams::builtin(Language::English, 5).is_err());
+    }
+
+    #[test]
+    fn test_score_decrypt() {
+        let plaintext = "Feindliche Kreuzer im Planquadrat gesichtet, erbitten weitere Befehle";
+        let ciphertext = "B II-V-III 03-14-22 KDW AQ EP TL".parse::<Enigma>().ok().unwrap().translate(plaintext);
+        let mut right: Enigma = "B II-V-III 03-14-22 KDW AQ EP TL".parse().ok().unwrap();
+        let mut wrong: Enigma = "B II-V-III 03-14-22 KDX AQ EP TL".parse().ok().unwrap();
+        let quadgrams = Ngrams::builtin(Language::German, 4).ok().unwrap();
+        assert!(quadgrams.score_text(&right.translate(&ciphertext))
+            > quadgrams.score_text(&wrong.translate(&ciphertext)) + 1.0);
+    }
+
+    #[test]
+    fn test_parse() {
+        let table: Ngrams = "# Custom\nAB 3\n\nba 1\n".parse().ok().unwrap();
+        assert_eq!(2, table.length());
+        assert!((table.log_probability("ab").unwrap() - 0.75f64.log10()).abs() < 1e-6);
+        assert!((table.log_probability("CC").unwrap() - 0.0025f64.log10()).abs() < 1e-6);
+        assert_eq!("Invalid N-gram Table: \"abc\" on line 2 (expected 2 letters)",
+                   "ab 1\nabc 2".parse::<Ngrams>().unwrap_err().to_string());
+        assert!("a1 2".parse::<Ngrams>().is_err());
+        assert!("ab many".parse::<Ngrams>().is_err());
+        assert!(" ab 2".parse::<Ngrams>().is_err());
+        assert!("# nothing".parse::<Ngrams>().is_err());
+        assert!("abcdef 1".parse::<Ngrams>().is_err());
+        assert_eq!(Language::English, "English".parse::<Language>().ok().unwrap());
+    }
+}
@@ -0,0 +1,239 @@
+use crate::machine::enigma::{EnigmaError, SlotKind};
+use crate::machine::model::Model;
+use crate::machine::rotor::Rotor;
+
+/// Letters of a rotor wiring for each of its 26 shifts (position less ring setting),
+/// indexed [shift][contact]
+pub(crate) type RotorTable = [[u8; 26]; 26];
+
+/// Wiring of a rotor as lookup tables, along with the positions where its notch engages
+pub(crate) struct RotorWiring {
+    pub forward: RotorTable,
+    pub reverse: RotorTable,
+    pub notches: [bool; 26],
+}
+
+impl RotorWiring {
+    pub fn new(mut rotor: Rotor) -> Self {
+        let mut forward: RotorTable = [[0; 26]; 26];
+        let mut reverse: RotorTable = [[0; 26]; 26];
+        let mut notches: [bool; 26] = [false; 26];
+        rotor.set_ring('a');
+        for shift in 0..26u8 {
+            rotor.set(letter(shift));
+            notches[shift as usize] = rotor.at_notch();
+            for contact in 0..26u8 {
+                forward[shift as usize][contact as usize] = index(rotor.translate_forward(letter(contact)));
+                reverse[shift as usize][contact as usize] = index(rotor.translate_reverse(letter(contact)));
+            }
+        }
+        Self {
+            forward,
+            reverse,
+            notches,
+        }
+    }
+}
+
+/// Three rotor Enigma with pawl stepping, reduced to lookup tables so that the codebreaking
+/// tools can try settings by the million. Letters are numbers 0..26 throughout.
+pub(crate) struct Scrambler {
+    /// Rotors from left to right
+    pub rotors: [RotorWiring; 3],
+    pub reflector: [u8; 26],
+    pub entry_forward: [u8; 26],
+    pub entry_reverse: [u8; 26],
+}
+
+impl Scrambler {
+    /// Scrambler of a model with three stepping rotors, for the named rotors (from left to
+    /// right) and reflector, or None if the model has another layout or a name is unknown
+    pub fn new(model: Model, rotors: &[String], reflector: &str) -> Option<Self> {
+        if model.slots() != [SlotKind::Stepping; 3] {
+            return None;
+        }
+        let [left, middle, right] = rotors else { return None; };
+        let rotors = [RotorWiring::new(model.rotor(left)?), RotorWiring::new(model.rotor(middle)?),
+            RotorWiring::new(model.rotor(right)?)];
+        let reflector = model.reflector(reflector)?;
+        let entry_wheel = model.entry_wheel();
+        Some(Self {
+            rotors,
+            reflector: std::array::from_fn(|x| index(reflector.translate(letter(x as u8)))),
+            entry_forward: std::array::from_fn(|x| index(entry_wheel.translate_forward(letter(x as u8)))),
+            entry_reverse: std::array::from_fn(|x| index(entry_wheel.translate_reverse(letter(x as u8)))),
+        })
+    }
+
+    /// Translate letters starting from the given rotor positions and ring settings (from
+    /// left to right), through a plugboard given as the partner of each letter
+    pub fn translate(&self, input: &[u8], positions: [u8; 3], rings: [u8; 3], plugboard: &[u8; 26],
+                     output: &mut Vec<u8>) {
+        let [left, middle, right] = &self.rotors;
+        let [mut l, mut m, mut r] = positions.map(|p| p as usize);
+        let [ring_l, ring_m, ring_r] = rings.map(|g| g as usize);
+        output.clear();
+        for &c in input {
+            // Pawls engage before anything moves, the middle rotor double steps on its notch
+            let step_left = middle.notches[m];
+            let step_middle = step_left || right.notches[r];
+            r = (r + 1) % 26;
+            if step_middle {
+                m = (m + 1) % 26;
+            }
+            if step_left {
+                l = (l + 1) % 26;
+            }
+            let (sl, sm, sr) = ((l + 26 - ring_l) % 26, (m + 26 - ring_m) % 26, (r + 26 - ring_r) % 26);
+            let mut x = self.entry_forward[plugboard[c as usize] as usize] as usize;
+            x = right.forward[sr][x] as usize;
+            x = middle.forward[sm][x] as usize;
+            x = left.forward[sl][x] as usize;
+            x = self.reflector[x] as usize;
+            x = left.reverse[sl][x] as usize;
+            x = middle.reverse[sm][x] as usize;
+            x = right.reverse[sr][x] as usize;
+            output.push(plugboard[self.entry_reverse[x] as usize]);
+        }
+    }
+}
+
+/// Every order of three different rotors from the catalogue of a model, from left to right
+pub(crate) fn rotor_orders(model: Model) -> Vec<Vec<String>> {
+    let names = model.rotor_names();
+    let mut orders: Vec<Vec<String>> = Vec::new();
+    for left in &names {
+        for middle in names.iter().filter(|r| *r != left) {
+            for right in names.iter().filter(|r| *r != left && *r != middle) {
+                orders.push(vec![left.to_string(), middle.to_string(), right.to_string()]);
+            }
+        }
+    }
+    orders
+}
+
+/// Why a model, reflector or rotor order can't be run on the scrambler
+pub(crate) enum SetupError {
+    InvalidModel(String),
+    InvalidRotorOrder(String),
+    Enigma(EnigmaError),
+}
+
+/// Check that a model has three stepping rotors and a plugboard, and find the reflector of
+/// its catalogue with the given name (ignoring case)
+pub(crate) fn resolve_reflector(model: Model, reflector: &str) -> Result<&'static str, SetupError> {
+    if model.slots() != [SlotKind::Stepping; 3] || !model.has_plugboard() {
+        return Err(SetupError::InvalidModel(
+            format!("{} (only three rotor machines with a plugboard can be broken)", model)));
+    }
+    model.reflector_names().into_iter()
+        .find(|name| name.eq_ignore_ascii_case(reflector))
+        .ok_or_else(|| SetupError::Enigma(EnigmaError::InvalidReflector(reflector.to_string())))
+}
+
+/// Parse rotor orders each given from left to right as "II-V-III", failing on the first
+/// which doesn't name three rotors of the model
+pub(crate) fn parse_rotor_orders(model: Model, orders: &[&str]) -> Result<Vec<Vec<String>>, SetupError> {
+    orders.iter().map(|order| {
+        let rotors: Vec<String> = order.split('-').map(|r| r.trim().to_string()).collect();
+        if rotors.len() != 3 || rotors.iter().any(|r| model.rotor(r).is_none()) {
+            return Err(SetupError::InvalidRotorOrder(order.to_string()));
+        }
+        Ok(rotors)
+    }).collect()
+}
+
+/// Most wires a plugboard can hold
+pub(crate) const MAX_WIRES: usize = 13;
+
+/// Plugboard with no wires, every letter is its own partner
+pub(crate) const EMPTY_PLUGBOARD: [u8; 26] = {
+    let mut plugboard = [0u8; 26];
+    let mut x = 0;
+    while x < 26 {
+        plugboard[x] = x as u8;
+        x += 1;
+    }
+    plugboard
+};
+
+/// Steepest ascent hill climb of a plugboard (the partner of each letter): each round tries
+/// connecting every pair of letters which aren't fixed (unplugging whatever they were
+/// connected to, or unplugging them if they were connected to each other) and takes the move
+/// which improves the score of the decrypt most. Returns the plugboard and its score.
+pub(crate) fn climb_plugboard(plugboard: [u8; 26], fixed: &[bool; 26], max_wires: usize,
+                              decrypt: impl Fn(&[u8; 26], &mut Vec<u8>),
+                              score: impl Fn(&[u8]) -> f64) -> ([u8; 26], f64) {
+    let mut output: Vec<u8> = Vec::new();
+    decrypt(&plugboard, &mut output);
+    let mut current = (plugboard, score(&output));
+    loop {
+        let mut best = current;
+        for a in (0..26u8).filter(|a| !fixed[*a as usize]) {
+            for b in (a + 1..26).filter(|b| !fixed[*b as usize]) {
+                let mut plugboard = current.0;
+                let connected = plugboard[a as usize] == b;
+                for x in [a, b] {
+                    let partner = plugboard[x as usize];
+                    plugboard[partner as usize] = partner;
+                    plugboard[x as usize] = x;
+                }
+                if !connected {
+                    plugboard[a as usize] = b;
+                    plugboard[b as usize] = a;
+                }
+                if wires(&plugboard) > max_wires {
+                    continue;
+                }
+                decrypt(&plugboard, &mut output);
+                let moved = score(&output);
+                if moved > best.1 {
+                    best = (plugboard, moved);
+                }
+            }
+        }
+        if best.1 <= current.1 {
+            return current;
+        }
+        current = best;
+    }
+}
+
+/// Number of wires in a plugboard
+pub(crate) fn wires(plugboard: &[u8; 26]) -> usize {
+    plugboard.iter().enumerate().filter(|(x, partner)| **partner as usize > *x).count()
+}
+
+/// Letters of a text as numbers 0..26, dropping everything else
+pub(crate) fn letter_indices(text: &str) -> Vec<u8> {
+    text.chars().filter(|c| c.is_ascii_alphabetic()).map(|c| index(c.to_ascii_lowercase())).collect()
+}
+
+pub(crate) fn index(letter: char) -> u8 {
+    letter as u8 - 97u8
+}
+
+pub(crate) fn letter(index: u8) -> char {
+    (index + 97u8) as char
+}
+
+#[cfg(test)]
+mod test_scrambler {
+    use super::*;
+    use crate::machine::enigma::Enigma;
+
+    #[test]
+    fn test_matches_enigma() {
+        let mut machine: Enigma = "B VI-IV-II 03-17-21 ADU AQ EP TL".parse().ok().unwrap();
+        let scrambler = Scrambler::new(Model::EnigmaI, &machine.rotor_names(), "B").unwrap();
+        let mut plugboard = EMPTY_PLUGBOARD;
+        for (a, b) in [(0, 16), (4, 15), (19, 11)] {
+            plugboard.swap(a, b);
+        }
+        let message = "doublesteppingacrossseveralturnoversofthemiddlerotor".repeat(20);
+        let mut output = Vec::new();
+        scrambler.translate(&letter_indices(&message), [0, 3, 20], [2, 16, 20], &plugboard, &mut output);
+        assert_eq!(machine.translate(&message), output.into_iter().map(letter).collect::<String>());
+        assert!(Scrambler::new(Model::M4, &machine.rotor_names(), "B-thin").is_none());
+    }
+}
@@ -0,0 +1,194 @@
+use crate::analysis::ciphertext_only::Candidate;
+use crate::analysis::ngram::{Language, Ngrams};
+use crate::analysis::scrambler::{climb_plugboard, index, letter, letter_indices, wires, EMPTY_PLUGBOARD,
+                                 MAX_WIRES};
+use crate::machine::enigma::{Enigma, EnigmaError};
+use crate::random::Random;
+use std::fmt;
+
+/// Recovery of the plugboard (Stecker) of a message whose rotor order, rings and start
+/// positions are known. The wires already in the machine are known pairs and stay where they
+/// are; the other letters are hill climbed by steepest ascent on the trigram score of the
+/// decrypt, starting once from the known pairs alone and then from random plugboards.
+#[derive(Clone, Debug)]
+pub struct SteckerSearch {
+    max_wires: usize,
+    restarts: usize,
+    seed: u64,
+    language: Language,
+}
+
+impl Default for SteckerSearch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SteckerSearch {
+    /// Search for at most 10 wires with 10 random restarts on German text
+    pub fn new() -> Self {
+        Self {
+            max_wires: 10,
+            restarts: 10,
+            seed: 0,
+            language: Language::German,
+        }
+    }
+
+    /// Most wires the plugboard is allowed to have, known pairs included (default 10)
+    pub fn set_max_wires(&mut self, max_wires: usize) -> Result<(), SteckerError> {
+        if max_wires > MAX_WIRES {
+            return Err(SteckerError::InvalidMaxWires(format!("{} (at most {})", max_wires, MAX_WIRES)));
+        }
+        self.max_wires = max_wires;
+        Ok(())
+    }
+
+    /// Number of climbs from random plugboards after the climb from the known pairs (default 10)
+    pub fn set_restarts(&mut self, restarts: usize) {
+        self.restarts = restarts;
+    }
+
+    /// Seed of the random plugboards, the same seed gives the same search (default 0)
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// Language the plaintext is expected in (default German)
+    pub fn set_language(&mut self, language: Language) {
+        self.language = language;
+    }
+
+    /// Search for the plugboard of a machine set up for the start of the ciphertext (characters
+    /// other than letters are ignored), returning the machine with the best plugboard found
+    pub fn run(&self, ciphertext: &str, machine: &Enigma) -> Result<Candidate, SteckerError> {
+        if !machine.model().has_plugboard() || machine.uhr().is_some() {
+            return Err(SteckerError::Enigma(EnigmaError::NoPlugboard(machine.model().to_string())));
+        }
+        let ciphertext = letter_indices(ciphertext);
+        if ciphertext.is_empty() {
+            return Err(SteckerError::InvalidCiphertext("no letters".to_string()));
+        }
+        let known = machine.plugboard_wires();
+        let mut fixed: [bool; 26] = [false; 26];
+        let mut start = EMPTY_PLUGBOARD;
+        for wire in known.split(',').filter(|wire| !wire.is_empty()) {
+            let (a, b) = (index(wire.chars().next().unwrap()), index(wire.chars().last().unwrap()));
+            start[a as usize] = b;
+            start[b as usize] = a;
+            fixed[a as usize] = true;
+            fixed[b as usize] = true;
+        }
+        if wires(&start) > self.max_wires {
+            return Err(SteckerError::InvalidMaxWires(
+                format!("{} (the machine already has {} wires)", self.max_wires, wires(&start))));
+        }
+
+        // With the rotors known, each position of the message is a fixed scrambler which the
+        // plugboard wraps on both sides
+        let mut bare = machine.clone();
+        bare.remove_plugboard_wires(&known)?;
+        let mut scramblers: Vec<[u8; 26]> = vec![[0; 26]; ciphertext.len()];
+        for x in 0..26u8 {
+            let column = bare.clone().translate(&letter(x).to_string().repeat(ciphertext.len()));
+            for (scrambler, c) in scramblers.iter_mut().zip(column.chars()) {
+                scrambler[x as usize] = index(c.to_ascii_lowercase());
+            }
+        }
+        let decrypt = |plugboard: &[u8; 26], output: &mut Vec<u8>| {
+            output.clear();
+            output.extend(ciphertext.iter().zip(&scramblers)
+                .map(|(c, scrambler)| plugboard[scrambler[plugboard[*c as usize] as usize] as usize]));
+        };
+        let trigrams = Ngrams::builtin(self.language, 3).ok().expect("built in trigram table");
+        let score = |letters: &[u8]| trigrams.score(letters);
+
+        let mut random = Random::new(self.seed);
+        let free: Vec<u8> = (0..26u8).filter(|x| !fixed[*x as usize]).collect();
+        let mut best = climb_plugboard(start, &fixed, self.max_wires, decrypt, score);
+        for _ in 0..self.restarts {
+            let mut plugboard = start;
+            let mut letters = free.clone();
+            random.shuffle(&mut letters);
+            let extra = random.below(self.max_wires - wires(&start) + 1);
+            for pair in letters.chunks_exact(2).take(extra) {
+                plugboard[pair[0] as usize] = pair[1];
+                plugboard[pair[1] as usize] = pair[0];
+            }
+            let climbed = climb_plugboard(plugboard, &fixed, self.max_wires, decrypt, score);
+            if climbed.1 > best.1 {
+                best = climbed;
+            }
+        }
+
+        let (plugboard, score) = best;
+        let mut solved = machine.clone();
+        for x in (0..26u8).filter(|x| !fixed[*x as usize] && plugboard[*x as usize] > *x) {
+            solved.add_plugboard_wire(&format!("{}-{}", letter(x), letter(plugboard[x as usize])))?;
+        }
+        let mut output: Vec<u8> = Vec::with_capacity(ciphertext.len());
+        decrypt(&plugboard, &mut output);
+        Ok(Candidate {
+            machine: solved,
+            score,
+            plaintext: output.into_iter().map(letter).collect(),
+        })
+    }
+}
+
+pub enum SteckerError {
+    InvalidMaxWires(String),
+    InvalidCiphertext(String),
+    Enigma(EnigmaError),
+}
+
+impl fmt::Display for SteckerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SteckerError::InvalidMaxWires(s) => { write!(f, "Invalid Max Wires: {}", s) }
+            SteckerError::InvalidCiphertext(s) => { write!(f, "Invalid Ciphertext: {}", s) }
+            SteckerError::Enigma(err) => { write!(f, "{}", err) }
+        }
+    }
+}
+
+impl From<EnigmaError> for SteckerError {
+    fn from(err: EnigmaError) -> Self {
+        SteckerError::Enigma(err)
+    }
+}
+
+#[cfg(test)]
+mod test_stecker {
+    use super::*;
+
+    const PLAINTEXT: &str = "anoberkommandoderwehrmachtfeindlichekraefteimraumostwaertsder\
+        stadtwurdenbeitagesanbruchgemeldetdiedivisionverlegtihrengefechtsstandnachdemdorfam\
+        flussuferundsichertdieuebergaengegegenangriffeausnordenmeldungenueberdiewetterlage\
+        folgenmitdernaechstenfunkverbindungzurmittagszeitendederdurchsage";
+
+    #[test]
+    fn test_run() {
+        let key = "B II-V-III 03-14-22 KDW AQ BJ CU EP FM GY HZ IN LT RX";
+        let ciphertext = key.parse::<Enigma>().ok().unwrap().translate(PLAINTEXT);
+        // Rotors known, along with two of the ten pairs
+        let partial: Enigma = "B II-V-III 03-14-22 KDW AQ EP".parse().ok().unwrap();
+        let mut search = SteckerSearch::new();
+        search.set_restarts(4);
+        let found = search.run(&ciphertext, &partial).ok().unwrap();
+        assert_eq!(key.parse::<Enigma>().ok().unwrap(), found.machine);
+        assert_eq!(PLAINTEXT, found.plaintext);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut search = SteckerSearch::new();
+        assert!(search.set_max_wires(14).is_err());
+        search.set_max_wires(1).ok().unwrap();
+        let partial: Enigma = "B II-V-III 03-14-22 KDW AQ EP".parse().ok().unwrap();
+        assert_eq!("Invalid Max Wires: 1 (the machine already has 2 wires)",
+                   search.run("abcdef", &partial).err().unwrap().to_string());
+        assert!(search.run("12345", &Enigma::default()).is_err());
+        assert!(search.run("abcdef", &Enigma::from_model(crate::machine::model::Model::SwissK)).is_err());
+    }
+}
@@ -0,0 +1,523 @@
+use enigmars::analysis::crib;
+use enigmars::machine::enigma::{Enigma, EnigmaError};
+use enigmars::machine::model::Model;
+use enigmars::machine::rotor_machine::RotorMachine;
+use enigmars::machine::stream::EnigmaReader;
+use enigmars::machine::text::{NonLetterPolicy, TranslateOptions};
+use enigmars::procedure::formatting::{self, Convention};
+use enigmars::procedure::key_sheet::{KeySheet, KeySheetError};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+
+/// Exit code when the machine can't be configured or the message can't be translated
+pub const EXIT_ENIGMA_ERROR: i32 = 1;
+/// Exit code when the command line arguments are malformed
+pub const EXIT_USAGE_ERROR: i32 = 2;
+/// Exit code when the input can't be read or the output can't be written
+pub const EXIT_IO_ERROR: i32 = 3;
+
+pub const USAGE: &str = "Usage: enigmars [OPTIONS] [INPUT FILE]
+       enigmars crib <CRIB> [CIPHERTEXT FILE]
+
+Run without any options to start the interactive menu. With options, the text to translate
+is read from the input file (or stdin if there is none) and written to stdout.
+
+The crib command lists every place the crib can sit under the ciphertext without a letter
+enciphering to itself, each with the menu it gives and its number of loops.
+
+Options:
+  -c, --config <FILE>          Load the machine from a configuration file, other options
+                               are applied on top of it
+      --save-config <FILE>     Save the configuration to a file instead of translating
+                               (unless an input file is given)
+  -k, --key <KEY>              Load the machine from a key string, for example
+                               \"B III-II-I 01-01-01 AAA AB CD EF\"
+      --sheet <FILE> --day <DAY>
+                               Load the machine from the key of a day of a key sheet
+      --generate-sheet <YEAR-MONTH>
+                               Print a key sheet for the month instead of translating,
+                               for the model given with --model
+      --seed <SEED>            Seed of the generated key sheet (default 0)
+  -m, --model <MODEL>          Machine model, for example EnigmaI, M4, K (default EnigmaI)
+  -r, --rotors <ROTORS>        Rotor order from left to right, for example I-II-III or Beta,II,IV,I
+  -g, --rings <RINGS>          Ring settings, as letters (AAA) or numbers (01-01-01)
+  -p, --positions <LETTERS>    Start positions of the rotors, for example ABC
+  -u, --reflector <REFLECTOR>  Reflector from the model's catalogue, for example B or B-thin
+      --reflector-position <LETTER>
+                               Setting of a settable reflector
+      --reflector-wiring <PAIRS>
+                               Plug the rewirable reflector (UKW-D) instead, with 12 pairs
+                               in Bletchley Park notation, as a-b,c-d or \"AB CD\"
+  -s, --plugs <PAIRS>          Plugboard pairs, as a-b,c-d or \"AB CD\"
+      --uhr <PAIRS>            Attach the Uhr instead of plugboard wires, with 10 ordered
+                               pairs (the first letters on the red plugs), as a-b,c-d or \"AB CD\"
+      --uhr-setting <NN>       Setting of the Uhr, 00 to 39 (default 00)
+      --non-letters <POLICY>   Handling of characters outside A-Z: pass (default), drop,
+                               error or transliterate (Ä to AE, ß to SS, ...)
+      --preserve-case          Keep uppercase letters uppercase in the output
+      --convention <army|navy> Prepare the plaintext the way it was sent (X for spaces,
+                               spelled out digits, CH as Q for the navy) and print the
+                               ciphertext in counted five (army) or four (navy) letter groups
+      --decipher               With --convention, read grouped ciphertext and undo the
+                               plaintext conventions in the output
+  -h, --help                   Show this message";
+
+/// Settings given on the command line
+#[derive(Default, Debug, PartialEq)]
+pub struct Options {
+    pub config: Option<String>,
+    pub save_config: Option<String>,
+    pub key: Option<String>,
+    pub sheet: Option<String>,
+    pub day: Option<String>,
+    pub generate_sheet: Option<String>,
+    pub seed: Option<String>,
+    pub model: Option<String>,
+    pub rotors: Option<String>,
+    pub rings: Option<String>,
+    pub positions: Option<String>,
+    pub reflector: Option<String>,
+    pub reflector_position: Option<String>,
+    pub reflector_wiring: Option<String>,
+    pub plugs: Option<String>,
+    pub uhr: Option<String>,
+    pub uhr_setting: Option<String>,
+    pub non_letters: Option<String>,
+    pub preserve_case: bool,
+    pub convention: Option<String>,
+    pub decipher: bool,
+    pub crib: Option<String>,
+    pub input: Option<String>,
+    pub help: bool,
+}
+
+pub enum CliError {
+    Usage(String),
+    Enigma(EnigmaError),
+    KeySheet(KeySheetError),
+    Io(io::Error),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Usage(s) => { write!(f, "{}\n\n{}", s, USAGE) }
+            CliError::Enigma(err) => { write!(f, "{}", err) }
+            CliError::KeySheet(err) => { write!(f, "{}", err) }
+            CliError::Io(err) => { write!(f, "IO Error: {}", err) }
+        }
+    }
+}
+
+impl From<EnigmaError> for CliError {
+    fn from(err: EnigmaError) -> Self {
+        CliError::Enigma(err)
+    }
+}
+
+impl From<KeySheetError> for CliError {
+    fn from(err: KeySheetError) -> Self {
+        CliError::KeySheet(err)
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(err: io::Error) -> Self {
+        CliError::Io(err)
+    }
+}
+
+impl CliError {
+    /// Process exit code for the error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Usage(_) => EXIT_USAGE_ERROR,
+            CliError::Enigma(_) | CliError::KeySheet(_) => EXIT_ENIGMA_ERROR,
+            CliError::Io(_) => EXIT_IO_ERROR,
+        }
+    }
+}
+
+/// Parse the command line arguments (not including the program name)
+pub fn parse_args(args: &[String]) -> Result<Options, CliError> {
+    let mut options = Options::default();
+    let mut args = args.iter().peekable();
+    if args.next_if(|arg| *arg == "crib").is_some() {
+        match args.next() {
+            Some(crib) => options.crib = Some(crib.clone()),
+            None => { return Err(CliError::Usage("Missing crib".to_string())); }
+        }
+    }
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if arg.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        if flag == "-h" || flag == "--help" {
+            options.help = true;
+            continue;
+        }
+        if flag == "--decipher" {
+            options.decipher = true;
+            continue;
+        }
+        if flag == "--preserve-case" {
+            options.preserve_case = true;
+            continue;
+        }
+        if !flag.starts_with('-') || flag == "-" {
+            if options.input.is_some() {
+                return Err(CliError::Usage(format!("Unexpected argument: {}", arg)));
+            }
+            options.input = Some(arg.clone());
+            continue;
+        }
+        let slot = match flag {
+            "-c" | "--config" => &mut options.config,
+            "--save-config" => &mut options.save_config,
+            "-k" | "--key" => &mut options.key,
+            "--sheet" => &mut options.sheet,
+            "--day" => &mut options.day,
+            "--generate-sheet" => &mut options.generate_sheet,
+            "--seed" => &mut options.seed,
+            "-m" | "--model" => &mut options.model,
+            "-r" | "--rotors" => &mut options.rotors,
+            "-g" | "--rings" => &mut options.rings,
+            "-p" | "--positions" => &mut options.positions,
+            "-u" | "--reflector" => &mut options.reflector,
+            "--reflector-position" => &mut options.reflector_position,
+            "--reflector-wiring" => &mut options.reflector_wiring,
+            "-s" | "--plugs" => &mut options.plugs,
+            "--uhr" => &mut options.uhr,
+            "--uhr-setting" => &mut options.uhr_setting,
+            "--non-letters" => &mut options.non_letters,
+            "--convention" => &mut options.convention,
+            _ => { return Err(CliError::Usage(format!("Unknown option: {}", flag))); }
+        };
+        let value = match inline_value.or_else(|| args.next().cloned()) {
+            Some(value) => value,
+            None => { return Err(CliError::Usage(format!("Missing value for {}", flag))); }
+        };
+        *slot = Some(value);
+    }
+    Ok(options)
+}
+
+/// Build the machine described by the options
+pub fn build_machine(options: &Options) -> Result<Enigma, CliError> {
+    let sources = [&options.config, &options.key, &options.model, &options.sheet];
+    if sources.iter().filter(|source| source.is_some()).count() > 1 {
+        return Err(CliError::Usage(
+            "Only one of --config, --key, --model and --sheet can be given".to_string()));
+    }
+    let mut machine = if let Some(path) = &options.config {
+        Enigma::from_config(&fs::read_to_string(path)?)?
+    } else if let Some(key) = &options.key {
+        key.parse::<Enigma>()?
+    } else if let Some(model) = &options.model {
+        Enigma::from_model(model.parse::<Model>()?)
+    } else if let Some(path) = &options.sheet {
+        let sheet = fs::read_to_string(path)?.parse::<KeySheet>()?;
+        let day = match options.day.as_deref().map(|day| day.parse::<u8>()) {
+            Some(Ok(day)) => day,
+            Some(Err(_)) => { return Err(CliError::Usage("--day must be a number".to_string())); }
+            None => { return Err(CliError::Usage("--sheet needs a --day".to_string())); }
+        };
+        sheet.machine(day)?
+    } else {
+        Enigma::default()
+    };
+    if let Some(rotors) = &options.rotors {
+        let names: Vec<&str> = rotors.split([',', '-', ' ']).filter(|r| !r.is_empty()).collect();
+        if names.len() != machine.rotor_count() {
+            return Err(CliError::Enigma(EnigmaError::InvalidRotor(rotors.clone())));
+        }
+        for (idx, name) in names.iter().enumerate() {
+            machine.choose_rotor(name, idx as u8)?;
+        }
+    }
+    if let Some(rings) = &options.rings {
+        machine.set_rings(&ring_letters(rings)?)?;
+    }
+    if let Some(positions) = &options.positions {
+        machine.set_rotors(positions)?;
+    }
+    if let Some(reflector) = &options.reflector {
+        machine.choose_reflector(reflector)?;
+    }
+    if let Some(wiring) = &options.reflector_wiring {
+        machine.rewire_reflector(&plug_wires(wiring))?;
+    }
+    if let Some(position) = &options.reflector_position {
+        machine.set_reflector(position)?;
+    }
+    if let Some(plugs) = &options.plugs {
+        if !plugs.trim().is_empty() {
+            machine.add_plugboard_wires(&plug_wires(plugs))?;
+        }
+    }
+    if let Some(pairs) = &options.uhr {
+        let setting = match options.uhr_setting.as_deref().map(|setting| setting.parse::<u8>()) {
+            Some(Ok(setting)) => setting,
+            Some(Err(_)) => { return Err(CliError::Usage("--uhr-setting must be a number".to_string())); }
+            None => 0,
+        };
+        machine.attach_uhr(&plug_wires(pairs), setting)?;
+    } else if options.uhr_setting.is_some() {
+        return Err(CliError::Usage("--uhr-setting needs an --uhr".to_string()));
+    }
+    Ok(machine)
+}
+
+/// Convert ring settings given as letters ("BUL") or numbers ("02-21-12") into letters
+fn ring_letters(rings: &str) -> Result<String, CliError> {
+    if rings.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Ok(rings.to_string());
+    }
+    rings.split([',', '-', ' ', '.'])
+        .filter(|r| !r.is_empty())
+        .map(|r| match r.parse::<u8>() {
+            Ok(n) if (1..=26).contains(&n) => Ok((n - 1 + b'a') as char),
+            _ => Err(CliError::Enigma(EnigmaError::InvalidRingSetting(rings.to_string()))),
+        })
+        .collect()
+}
+
+/// Convert plugboard pairs given as "AB CD" into the "a-b,c-d" form, other forms are
+/// passed on unchanged
+fn plug_wires(plugs: &str) -> String {
+    if plugs.contains('-') {
+        return plugs.to_string();
+    }
+    plugs.split_whitespace()
+        .map(|pair| pair.chars().map(|c| c.to_string()).collect::<Vec<String>>().join("-"))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Generate the key sheet asked for by the options
+pub fn generate_sheet(options: &Options) -> Result<KeySheet, CliError> {
+    let month = options.generate_sheet.as_deref().unwrap_or_default();
+    let (year, month) = match month.split_once('-').map(|(y, m)| (y.parse::<u16>(), m.parse::<u8>())) {
+        Some((Ok(year), Ok(month))) => (year, month),
+        _ => { return Err(CliError::Usage(format!("Invalid month {}, expected YEAR-MONTH", month))); }
+    };
+    let seed: u64 = match options.seed.as_deref().map(|seed| seed.parse::<u64>()) {
+        Some(Ok(seed)) => seed,
+        Some(Err(_)) => { return Err(CliError::Usage("--seed must be a number".to_string())); }
+        None => 0,
+    };
+    let model = match &options.model {
+        Some(model) => model.parse::<Model>()?,
+        None => Model::EnigmaI,
+    };
+    Ok(KeySheet::generate(model, year, month, seed)?)
+}
+
+/// Translate the text with the machine, applying the message conventions asked for by the
+/// options
+pub fn translate_text(options: &Options, machine: &mut Enigma, text: &str) -> Result<String, CliError> {
+    let convention = match options.convention.as_deref().map(|c| c.parse::<Convention>()) {
+        Some(Ok(convention)) => convention,
+        Some(Err(err)) => { return Err(CliError::Usage(err.to_string())); }
+        None if options.decipher => {
+            return Err(CliError::Usage("--decipher needs a --convention".to_string()));
+        }
+        None => { return Ok(machine.translate_with(text, &translate_options(options)?)?); }
+    };
+    if options.non_letters.is_some() || options.preserve_case {
+        return Err(CliError::Usage(
+            "--non-letters and --preserve-case can't be used with a --convention".to_string()));
+    }
+    if options.decipher {
+        let plaintext = machine.translate(&formatting::parse_groups(text));
+        Ok(format!("{}\n", formatting::postprocess(&plaintext, convention)))
+    } else {
+        let ciphertext = machine.translate(&formatting::preprocess(text, convention));
+        Ok(format!("{}\n", formatting::format_groups(&ciphertext, convention.group_size())))
+    }
+}
+
+/// Options for translating text without a message convention
+fn translate_options(options: &Options) -> Result<TranslateOptions, CliError> {
+    let non_letters = match options.non_letters.as_deref() {
+        None | Some("pass") => NonLetterPolicy::PassThrough,
+        Some("drop") => NonLetterPolicy::Drop,
+        Some("error") => NonLetterPolicy::Error,
+        Some("transliterate") => NonLetterPolicy::Transliterate,
+        Some(other) => {
+            return Err(CliError::Usage(format!("Unknown --non-letters policy: {}", other)));
+        }
+    };
+    Ok(TranslateOptions {
+        non_letters,
+        preserve_case: options.preserve_case,
+    })
+}
+
+/// List the admissible placements of the crib under the ciphertext, followed by the one with
+/// the most loops
+pub fn crib_placements(crib: &str, ciphertext: &str) -> String {
+    let placements = crib::placements(crib, ciphertext);
+    let mut output: String = placements.iter().map(|placement| format!("{}\n", placement)).collect();
+    match placements.iter().max_by_key(|placement| (placement.loops(), std::cmp::Reverse(placement.offset))) {
+        Some(best) => output.push_str(&format!("{} placements, the most loops ({}) at offset {}\n",
+                                               placements.len(), best.loops(), best.offset)),
+        None => output.push_str("No placements\n"),
+    }
+    output
+}
+
+/// Run the non-interactive mode, translating the input into stdout
+pub fn run(options: &Options) -> Result<(), CliError> {
+    if options.help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    if options.generate_sheet.is_some() {
+        io::stdout().lock().write_all(generate_sheet(options)?.to_string().as_bytes())?;
+        return Ok(());
+    }
+    if let Some(crib) = &options.crib {
+        let mut ciphertext = String::new();
+        match options.input.as_deref() {
+            Some("-") | None => { io::stdin().read_to_string(&mut ciphertext)?; }
+            Some(path) => { ciphertext = fs::read_to_string(path)?; }
+        }
+        io::stdout().lock().write_all(crib_placements(crib, &ciphertext).as_bytes())?;
+        return Ok(());
+    }
+    let mut machine = build_machine(options)?;
+    if let Some(path) = &options.save_config {
+        fs::write(path, machine.to_config())?;
+        if options.input.is_none() {
+            return Ok(());
+        }
+    }
+    let mut input: Box<dyn Read> = match options.input.as_deref() {
+        Some("-") | None => Box::new(io::stdin().lock()),
+        Some(path) => Box::new(fs::File::open(path)?),
+    };
+    let mut stdout = io::stdout().lock();
+    // Plain translation is streamed, so the input can be of any size
+    if options.convention.is_none() && translate_options(options)? == TranslateOptions::default() {
+        io::copy(&mut EnigmaReader::new(input, machine), &mut stdout)?;
+        stdout.flush()?;
+        return Ok(());
+    }
+    let mut text = String::new();
+    input.read_to_string(&mut text)?;
+    stdout.write_all(translate_text(options, &mut machine, &text)?.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_cli {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse_args(&args(&["-m", "M4", "--rotors=Beta-II-IV-I", "--rings",
+            "01-01-01-22", "-p", "VJNA", "-u", "B-thin", "-s", "AT BL DF", "message.txt"]))
+            .ok().unwrap();
+        assert_eq!(Some("M4".to_string()), options.model);
+        assert_eq!(Some("Beta-II-IV-I".to_string()), options.rotors);
+        assert_eq!(Some("message.txt".to_string()), options.input);
+        assert!(parse_args(&args(&["--rotor", "I"])).is_err());
+        assert!(parse_args(&args(&["--rotors"])).is_err());
+        assert!(parse_args(&args(&["a.txt", "b.txt"])).is_err());
+        let options = parse_args(&args(&["crib", "wetter", "message.txt"])).ok().unwrap();
+        assert_eq!(Some("wetter".to_string()), options.crib);
+        assert_eq!(Some("message.txt".to_string()), options.input);
+        assert!(parse_args(&args(&["crib"])).is_err());
+    }
+
+    #[test]
+    fn test_build_machine() {
+        let options = parse_args(&args(&["-m", "M4", "-r", "Beta-II-IV-I", "-g", "01-01-01-22",
+            "-p", "VJNA", "-u", "B-thin", "-s", "AT BL DF GJ HM NW OP QY RZ VX"])).ok().unwrap();
+        let mut machine = build_machine(&options).ok().unwrap();
+        assert_eq!("vonvonjlooksj", machine.translate("NCZWVUSXPNYMI"));
+        let options = parse_args(&args(&["-r", "I-II"])).ok().unwrap();
+        assert_eq!(EXIT_ENIGMA_ERROR, build_machine(&options).err().unwrap().exit_code());
+        let options = parse_args(&args(&["-g", "00-01-01"])).ok().unwrap();
+        assert!(build_machine(&options).is_err());
+        let options = parse_args(&args(&["-k", "B III-II-I 01-01-01 AAA", "-p", "ADU"])).ok().unwrap();
+        assert_eq!("B III-II-I 01-01-01 ADU", build_machine(&options).ok().unwrap().to_string());
+        let options = parse_args(&args(&["--generate-sheet", "1941-05", "--seed", "99"])).ok().unwrap();
+        let sheet = generate_sheet(&options).ok().unwrap();
+        assert_eq!(31, sheet.days().len());
+        let options = parse_args(&args(&["--generate-sheet", "1941"])).ok().unwrap();
+        assert_eq!(EXIT_USAGE_ERROR, generate_sheet(&options).err().unwrap().exit_code());
+        let options = parse_args(&args(&["-c", "enigma.conf", "-m", "M4"])).ok().unwrap();
+        assert_eq!(EXIT_USAGE_ERROR, build_machine(&options).err().unwrap().exit_code());
+    }
+
+    #[test]
+    fn test_reflector_wiring_and_uhr() {
+        let mut expected = Enigma::default();
+        _ = expected.attach_uhr("a-b,c-d,e-f,g-h,i-j,k-l,m-n,o-p,q-r,s-t", 27);
+        let options = parse_args(&args(&["--uhr", "AB CD EF GH IJ KL MN OP QR ST",
+            "--uhr-setting", "27"])).ok().unwrap();
+        assert_eq!(expected, build_machine(&options).ok().unwrap());
+        let options = parse_args(&args(&["--uhr-setting", "27"])).ok().unwrap();
+        assert_eq!(EXIT_USAGE_ERROR, build_machine(&options).err().unwrap().exit_code());
+        let options = parse_args(&args(&["--uhr", "AB CD", "--uhr-setting", "xx"])).ok().unwrap();
+        assert_eq!(EXIT_USAGE_ERROR, build_machine(&options).err().unwrap().exit_code());
+        let options = parse_args(&args(&["--uhr", "AB CD"])).ok().unwrap();
+        assert_eq!(EXIT_ENIGMA_ERROR, build_machine(&options).err().unwrap().exit_code());
+
+        let wiring = "a-c,b-o,d-i,e-m,f-g,h-r,k-z,l-s,n-u,p-t,q-w,v-x";
+        let mut expected = Enigma::default();
+        _ = expected.rewire_reflector(wiring);
+        let options = parse_args(&args(&["--reflector-wiring", "AC BO DI EM FG HR KZ LS NU PT QW VX"]))
+            .ok().unwrap();
+        assert_eq!(expected, build_machine(&options).ok().unwrap());
+        let options = parse_args(&args(&["-m", "M4", "--reflector-wiring", wiring])).ok().unwrap();
+        assert_eq!(EXIT_ENIGMA_ERROR, build_machine(&options).err().unwrap().exit_code());
+    }
+
+    #[test]
+    fn test_crib_placements() {
+        assert_eq!("   0: 0:AB 1:BC 2:CA (1 loops)\n   1: 1:AC 2:BA 3:CA (1 loops)\n\
+                    2 placements, the most loops (1) at offset 0\n",
+                   crib_placements("abc", "BCAAB"));
+        assert_eq!("No placements\n", crib_placements("abc", "AB"));
+    }
+
+    #[test]
+    fn test_translate_text() {
+        let options = parse_args(&args(&["--convention", "navy", "-p", "QEV"])).ok().unwrap();
+        let ciphertext = translate_text(&options, &mut build_machine(&options).ok().unwrap(),
+                                        "Achtung, 3 Boote in Sicht.").ok().unwrap();
+        // AQTUNGXDREIXBOOTEXINXSIQT in seven groups
+        assert_eq!(7, ciphertext.split_whitespace().count() - 1);
+        assert!(ciphertext.trim_end().ends_with(" 7"));
+        let options = parse_args(&args(&["--convention=navy", "--decipher", "-p", "QEV"])).ok().unwrap();
+        assert_eq!("achtung 3 boote in sicht\n",
+                   translate_text(&options, &mut build_machine(&options).ok().unwrap(), &ciphertext)
+                       .ok().unwrap());
+        let options = parse_args(&args(&["--non-letters", "drop", "--preserve-case"])).ok().unwrap();
+        assert_eq!("Sk", translate_text(&options, &mut Enigma::default(), "A, b.").ok().unwrap());
+        let options = parse_args(&args(&["--non-letters=error"])).ok().unwrap();
+        assert_eq!(EXIT_ENIGMA_ERROR, translate_text(&options, &mut Enigma::default(), "a b")
+            .err().unwrap().exit_code());
+        assert_eq!(Enigma::default().translate("abc\n"),
+                   translate_text(&options, &mut Enigma::default(), "abc\n").ok().unwrap());
+        let options = parse_args(&args(&["--decipher"])).ok().unwrap();
+        assert_eq!(EXIT_USAGE_ERROR, translate_text(&options, &mut Enigma::default(), "")
+            .err().unwrap().exit_code());
+        for extra in ["--preserve-case", "--non-letters=drop"] {
+            let options = parse_args(&args(&["--convention", "army", extra])).ok().unwrap();
+            assert_eq!(EXIT_USAGE_ERROR, translate_text(&options, &mut Enigma::default(), "feind")
+                .err().unwrap().exit_code());
+        }
+    }
+}
@@ -1 +1,4 @@
-pub mod machine;
\ No newline at end of file
+pub mod analysis;
+pub mod machine;
+pub mod procedure;
+mod random;
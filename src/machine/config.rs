@@ -0,0 +1,204 @@
+use crate::machine::enigma::{Enigma, EnigmaError};
+use crate::machine::model::{Model, REWIRABLE_REFLECTOR};
+
+/// Settings of the configuration file, in the order they are written and applied
+const SETTINGS: [&str; 10] = ["model", "rotors", "rings", "positions", "reflector",
+    "reflector wiring", "reflector position", "entry wheel", "plugboard", "uhr"];
+
+/// Plain text machine configuration, with one "<setting>: <value>" line per setting, for
+/// example
+///
+/// ```text
+/// model: EnigmaI
+/// rotors: II,I,III
+/// rings: xmv
+/// positions: abl
+/// reflector: A
+/// reflector position: a
+/// entry wheel: abcdefghijklmnopqrstuvwxyz
+/// plugboard: a-m,f-i,n-v,p-s,t-u,w-z
+/// ```
+///
+/// Blank lines and lines starting with '#' are ignored, and settings which are left out keep
+/// the defaults of the model. The rotors line also gives the slots of the machine, a rotor
+/// marked by a '*' (as in "I*,II,III,IV") being fixed. The rewirable reflector is given as "reflector: D" along with
+/// a "reflector wiring" line, and the Uhr as "uhr: <pairs> <setting>" in place of the
+/// plugboard line.
+impl Enigma {
+    /// Serialize the configuration of the machine, which can be read back with `from_config`
+    pub fn to_config(&self) -> String {
+        let mut config = String::from("# Enigma configuration\n");
+        config.push_str(&format!("model: {}\n", self.model()));
+        config.push_str(&format!("rotors: {}\n", self.marked_rotor_names().join(",")));
+        config.push_str(&format!("rings: {}\n", self.rings()));
+        config.push_str(&format!("positions: {}\n", self.positions()));
+        config.push_str(&format!("reflector: {}\n", self.reflector_name()));
+        if let Some(wiring) = self.reflector_wiring() {
+            config.push_str(&format!("reflector wiring: {}\n", wiring));
+        }
+        config.push_str(&format!("reflector position: {}\n", self.reflector_position()));
+        config.push_str(&format!("entry wheel: {}\n", self.entry_wheel_wiring()));
+        if self.model().has_plugboard() {
+            match self.uhr() {
+                Some((pairs, setting)) => config.push_str(&format!("uhr: {} {:02}\n", pairs, setting)),
+                None => config.push_str(&format!("plugboard: {}\n", self.plugboard_wires())),
+            }
+        }
+        config
+    }
+
+    /// Parse a machine from a configuration written by `to_config` (or by hand). Errors
+    /// give the line of the offending setting.
+    pub fn from_config(config: &str) -> Result<Self, EnigmaError> {
+        let mut settings: Vec<(usize, &str, &str)> = Vec::new();
+        for (idx, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => {
+                    return Err(EnigmaError::InvalidConfiguration(
+                        format!("line {}: expected <setting>: <value>", idx + 1)));
+                }
+            };
+            let Some(key) = SETTINGS.iter().find(|setting| setting.eq_ignore_ascii_case(key)) else {
+                return Err(EnigmaError::InvalidConfiguration(
+                    format!("line {}: unknown setting {}", idx + 1, key)));
+            };
+            if settings.iter().any(|(_, other, _)| other == key) {
+                return Err(EnigmaError::InvalidConfiguration(
+                    format!("line {}: {} is set twice", idx + 1, key)));
+            }
+            settings.push((idx + 1, key, value));
+        }
+        let find = |key: &str| settings.iter().find(|(_, other, _)| *other == key);
+        let model = match find("model") {
+            Some((line, _, value)) => value.parse::<Model>().map_err(|err| at_line(*line, err))?,
+            None => Model::EnigmaI,
+        };
+        // The rewirable reflector is plugged by its wiring line, without one the machine would
+        // quietly keep its default reflector
+        if let Some((line, _, value)) = find("reflector") {
+            if value.eq_ignore_ascii_case(REWIRABLE_REFLECTOR) && find("reflector wiring").is_none() {
+                return Err(EnigmaError::InvalidConfiguration(
+                    format!("line {}: reflector {} needs a reflector wiring", line, value)));
+            }
+        }
+        // The rotors line gives the slots of the machine as well, with fixed rotors marked by
+        // a '*' as in the key string
+        let mut machine = match find("rotors") {
+            Some((line, _, value)) => {
+                let names: Vec<&str> = value.split(',').map(|name| name.trim()).collect();
+                Enigma::from_rotor_names(model, &names).map_err(|err| at_line(*line, err))?
+            }
+            None => Enigma::from_model(model),
+        };
+        for key in SETTINGS.iter().skip(2) {
+            if let Some((line, _, value)) = find(key) {
+                machine.apply_setting(key, value).map_err(|err| at_line(*line, err))?;
+            }
+        }
+        Ok(machine)
+    }
+
+    /// Apply a single setting of a configuration file to the machine
+    fn apply_setting(&mut self, key: &str, value: &str) -> Result<(), EnigmaError> {
+        match key {
+            "rings" => self.set_rings(value),
+            "positions" => self.set_rotors(value),
+            // The rewirable reflector is plugged by its wiring line
+            "reflector" if self.model().has_rewirable_reflector()
+                && value.eq_ignore_ascii_case(REWIRABLE_REFLECTOR) => Ok(()),
+            "reflector" => self.choose_reflector(value),
+            "reflector wiring" => self.rewire_reflector(value),
+            "reflector position" => self.set_reflector(value),
+            "entry wheel" if value.chars().count() == 26 => self.set_entry_wheel_wiring(value),
+            "entry wheel" => self.choose_entry_wheel(value),
+            "plugboard" if value.is_empty() => Ok(()),
+            "plugboard" => self.add_plugboard_wires(value),
+            "uhr" => {
+                let (pairs, setting) = value.rsplit_once(' ').unwrap_or((value, ""));
+                match setting.trim().parse::<u8>() {
+                    Ok(setting) => self.attach_uhr(pairs.trim(), setting),
+                    Err(_) => Err(EnigmaError::InvalidUhr(format!("{} (missing setting)", value))),
+                }
+            }
+            _ => Err(EnigmaError::InvalidConfiguration(format!("unknown setting {}", key))),
+        }
+    }
+}
+
+/// Prefix an error with the line of the configuration it came from
+fn at_line(line: usize, err: EnigmaError) -> EnigmaError {
+    EnigmaError::InvalidConfiguration(format!("line {}: {}", line, err))
+}
+
+#[cfg(test)]
+mod test_config {
+    use crate::machine::enigma::{Enigma, SlotKind};
+    use crate::machine::model::Model;
+
+    #[test]
+    fn test_round_trip() {
+        let mut machine = Enigma::default();
+        _ = machine.choose_rotor("II", 0);
+        _ = machine.choose_rotor("I", 1);
+        _ = machine.set_rings("xmv");
+        _ = machine.set_rotors("abl");
+        _ = machine.add_plugboard_wires("a-m,f-i,n-v,p-s,t-u,w-z");
+        let config = machine.to_config();
+        let mut loaded = Enigma::from_config(&config).ok().unwrap();
+        assert_eq!(config, loaded.to_config());
+        assert_eq!(machine.translate("gcdseahugwtqgrkvlfgxucalxvymigmmnmfdxtgnvhvrmmevouyf"),
+                   loaded.translate("gcdseahugwtqgrkvlfgxucalxvymigmmnmfdxtgnvhvrmmevouyf"));
+
+        let mut m4 = Enigma::m4();
+        _ = m4.choose_rotor("gamma", 0);
+        _ = m4.choose_reflector("c-thin");
+        let loaded = Enigma::from_config(&m4.to_config()).ok().unwrap();
+        assert_eq!(Model::M4, loaded.model());
+        assert_eq!(m4.to_config(), loaded.to_config());
+    }
+
+    #[test]
+    fn test_slots() {
+        let mut machine = Enigma::with_slots(&[SlotKind::Fixed, SlotKind::Stepping,
+            SlotKind::Stepping, SlotKind::Stepping]);
+        _ = machine.set_rotors("qaev");
+        let config = machine.to_config();
+        assert!(config.contains("rotors: I*,II,III,IV\n"));
+        let mut loaded = Enigma::from_config(&config).ok().unwrap();
+        assert_eq!(machine.slot_kinds(), loaded.slot_kinds());
+        assert_eq!(config, loaded.to_config());
+        assert_eq!(machine.translate("fixedrotorsneverstep"), loaded.translate("fixedrotorsneverstep"));
+    }
+
+    #[test]
+    fn test_rewirable_reflector_and_uhr() {
+        let mut machine = Enigma::default();
+        _ = machine.rewire_reflector("a-c,b-o,d-i,e-m,f-g,h-r,k-z,l-s,n-u,p-t,q-w,v-x");
+        _ = machine.attach_uhr("a-b,c-d,e-f,g-h,i-j,k-l,m-n,o-p,q-r,s-t", 27);
+        let config = machine.to_config();
+        assert!(config.contains("reflector: D\n"));
+        assert!(config.contains("uhr: a-b,c-d,e-f,g-h,i-j,k-l,m-n,o-p,q-r,s-t 27\n"));
+        let mut loaded = Enigma::from_config(&config).ok().unwrap();
+        assert_eq!(machine.translate("enigmauhr"), loaded.translate("enigmauhr"));
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let error = |config: &str| Enigma::from_config(config).err().unwrap().to_string();
+        assert_eq!("Invalid Configuration: line 2: unknown setting colour",
+                   error("model: M4\ncolour: red"));
+        assert_eq!("Invalid Configuration: line 3: Invalid Rotor: IX",
+                   error("# comment\n\nrotors: I,II,IX"));
+        assert_eq!("Invalid Configuration: line 1: expected <setting>: <value>", error("rotors"));
+        assert!(Enigma::from_config("model: K\nplugboard: a-b").is_err());
+        assert!(Enigma::from_config("rings: aaa\nrings: bbb").is_err());
+        assert!(Enigma::from_config("").is_ok());
+        assert_eq!("Invalid Configuration: line 2: reflector D needs a reflector wiring",
+                   error("rotors: I,II,III\nreflector: D"));
+    }
+}
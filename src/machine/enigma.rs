@@ -1,157 +1,466 @@
+use crate::machine::entry_wheel;
+use crate::machine::model;
 use crate::machine::plugboard;
 use crate::machine::reflector;
 use crate::machine::rotor;
+use crate::machine::rotor_machine::RotorMachine;
 use std::fmt;
+use std::str::FromStr;
+
+/// How the rotor in a slot of the machine moves when a key is pressed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SlotKind {
+    /// Rotor is moved by the pawl and ratchet stepping mechanism
+    Stepping,
+    /// Rotor can be set by hand but never steps, for example a stator
+    Fixed,
+    /// Non-stepping Greek wheel (Zusatzwalze) of the naval M4, only holds Beta or Gamma
+    /// and is placed next to a thin reflector
+    Greek,
+}
+
+/// What can be seen after pressing a key of the machine
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Keystroke {
+    /// Lamp lit by the key
+    pub lamp: char,
+    /// Letters in the rotor windows after the rotors moved, from left to right
+    pub windows: String,
+    /// Setting of the reflector, which turns with the rotors on the cog stepped models
+    pub reflector_position: char,
+}
+
+/// A rotor together with the slot of the machine it sits in
+#[derive(Clone, PartialEq, Debug)]
+struct RotorSlot {
+    kind: SlotKind,
+    /// Name of the rotor in the catalogue of the machine's model
+    name: String,
+    rotor: rotor::Rotor,
+}
 
 /// Struct representing the Enigma Machine
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct Enigma {
-    /// Rotors placed in the machine
-    rotors: [rotor::Rotor; 3],
+    /// Model of the machine, which decides the available rotors and reflectors
+    model: model::Model,
+    /// Rotors placed in the machine, ordered from left (next to the reflector)
+    /// to right (next to the plugboard)
+    slots: Vec<RotorSlot>,
+    /// Entry wheel between the plugboard and the rightmost rotor
+    entry_wheel: entry_wheel::EntryWheel,
     /// Plugboard configuration for the machine
     plugboard: plugboard::Plugboard,
+    /// Name of the reflector in the catalogue of the machine's model
+    reflector_name: String,
     /// Reflector in the machine
     reflector: reflector::Reflector,
 }
 
+/// Suffix marking a fixed rotor in the key string
+const FIXED_ROTOR_MARK: char = '*';
+
+/// The key string of the machine, in the form
+/// `[model] reflector[@position] rotors rings positions [ETW:wiring] [UHR:setting] [pairs...]`,
+/// for example "B III-II-I 01-01-01 AAA AB CD EF". The model is left out for the Enigma I,
+/// the reflector position and entry wheel only appear when they differ from the defaults,
+/// and the rewirable reflector is written as "D:AC,BO,...". Fixed rotors are marked with
+/// a '*'. The alternate form (`{:#}`) lists the full configuration of every component.
 impl fmt::Display for Enigma {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !f.alternate() {
+            return write!(f, "{}", self.key());
+        }
+        writeln!(f, "Model: {}", self.model)?;
+        writeln!(f, "Rotor Configuration:")?;
+        for (idx, slot) in self.slots.iter().enumerate() {
+            match slot.kind {
+                SlotKind::Stepping => writeln!(f, "\tRotor {} ({}):{}", idx + 1, slot.name, slot.rotor)?,
+                SlotKind::Fixed => writeln!(f, "\tRotor {} ({}, Fixed):{}", idx + 1, slot.name, slot.rotor)?,
+                SlotKind::Greek => writeln!(f, "\tRotor {} ({}, Greek Wheel):{}", idx + 1, slot.name, slot.rotor)?,
+            }
+        }
         write!(f,
-               "Rotor Configuration:
-\tRotor 1:{}
-\tRotor 2:{}
-\tRotor 3:{}
-Reflector Configuration:
+               "Reflector Configuration:
+\t{}: {}{}
+Entry Wheel Configuration:
 \t{}
 Plugboard Configuration:
 \t{}
 ",
-               self.rotors[0], self.rotors[1], self.rotors[2],
-               self.reflector,
+               self.reflector_name, self.reflector,
+               if self.reflector_name == model::REWIRABLE_REFLECTOR {
+                   format!(", Pairs: {}", self.reflector.pairs())
+               } else {
+                   String::new()
+               },
+               self.entry_wheel,
                self.plugboard
         )
     }
 }
 
+impl Default for Enigma {
+    /// Create a default enigma configuration with rotors I,II,III and reflector A, with
+    /// no wires in the plugboard
+    fn default() -> Self {
+        Self::from_model(model::Model::EnigmaI)
+    }
+}
+
 impl Enigma {
-    /// Create a new enigma machine with the provided rotors, plugboard, and reflector
-    pub(crate) fn new(r1: rotor::Rotor, r2: rotor::Rotor, r3: rotor::Rotor,
-               plugboard: plugboard::Plugboard, reflector: reflector::Reflector) -> Self {
-        let rotors: [rotor::Rotor; 3] = [r1, r2, r3];
+    /// Create a new enigma machine of the given model with the rotors in the given slots,
+    /// filled in order from the model's catalogue (Greek slots with the first Greek wheel),
+    /// the first reflector in the catalogue, and no wires in the plugboard
+    fn new(model: model::Model, kinds: &[SlotKind]) -> Self {
+        let rotor_names = model.rotor_names();
+        let mut next_rotor = rotor_names.iter().cycle();
+        let slots: Vec<RotorSlot> = kinds.iter().map(|kind| {
+            let (name, rotor) = match kind {
+                SlotKind::Greek => {
+                    let name = model.greek_wheel_names()[0];
+                    (name, model.greek_wheel(name))
+                }
+                _ => {
+                    let name = next_rotor.next().expect("Cycled catalogue is never empty");
+                    (*name, model.rotor(name))
+                }
+            };
+            RotorSlot {
+                kind: *kind,
+                name: name.to_string(),
+                rotor: rotor.expect("Names come from the model catalogue"),
+            }
+        }).collect();
+        let reflector_name = model.reflector_names()[0];
         Self {
-            rotors,
-            plugboard,
-            reflector,
+            model,
+            slots,
+            entry_wheel: model.entry_wheel(),
+            plugboard: plugboard::Plugboard::new(),
+            reflector_name: reflector_name.to_string(),
+            reflector: model.reflector(reflector_name).expect("Name comes from the model catalogue"),
         }
     }
 
-    /// Create a default enigma configuration with rotors I,II,III and reflector A, with
-    /// no wires in the plugboard
-    pub fn default() -> Self {
-        Self::new(rotor::Rotor::new_i(), rotor::Rotor::new_ii(), rotor::Rotor::new_iii(),
-                  plugboard::Plugboard::new(),  reflector::Reflector::new_a())
+    /// Create an enigma machine of the given model, with the first rotors and reflector
+    /// from the model's catalogue
+    pub fn from_model(model: model::Model) -> Self {
+        Self::new(model, &model.slots())
     }
 
-    /// Set a rotor in a given position
-    pub fn choose_rotor(&mut self, rotor: &str, position: u8) -> Result<(), EnigmaError> {
-        match rotor {
-            "I" => {
-                self.rotors[position as usize] = rotor::Rotor::new_i();
-                Ok(())
-            }
-            "II" => {
-                self.rotors[position as usize] = rotor::Rotor::new_ii();
-                Ok(())
-            }
-            "III" => {
-                self.rotors[position as usize] = rotor::Rotor::new_iii();
-                Ok(())
-            }
-            "IV" => {
-                self.rotors[position as usize] = rotor::Rotor::new_iv();
-                Ok(())
-            }
-            "V" => {
-                self.rotors[position as usize] = rotor::Rotor::new_v();
-                Ok(())
-            }
-            "VI" => {
-                self.rotors[position as usize] = rotor::Rotor::new_vi();
-                Ok(())
-            }
-            "VII" => {
-                self.rotors[position as usize] = rotor::Rotor::new_vii();
-                Ok(())
+    /// Create an enigma machine with the given slots, ordered from left to right. The
+    /// stepping and fixed slots are filled with rotors I,II,III,... in order and Greek
+    /// slots with Beta. The reflector is the thin reflector B if there is a Greek wheel,
+    /// and reflector A otherwise, with no wires in the plugboard.
+    pub fn with_slots(kinds: &[SlotKind]) -> Self {
+        if kinds.contains(&SlotKind::Greek) {
+            Self::new(model::Model::M4, kinds)
+        } else {
+            Self::new(model::Model::EnigmaI, kinds)
+        }
+    }
+
+    /// Create an enigma machine of the given model holding the named rotors from left to
+    /// right, with the kind of each slot taken from its name: rotors marked by a '*' are
+    /// fixed, Greek wheels go in Greek slots, and the other rotors step
+    pub(crate) fn from_rotor_names(model: model::Model, names: &[&str]) -> Result<Self, EnigmaError> {
+        let rotors: Vec<(&str, SlotKind)> = names.iter()
+            .map(|name| match name.strip_suffix(FIXED_ROTOR_MARK) {
+                Some(name) => (name, SlotKind::Fixed),
+                None if model.greek_wheel_names().iter().any(|g| g.eq_ignore_ascii_case(name)) => {
+                    (*name, SlotKind::Greek)
+                }
+                None => (*name, SlotKind::Stepping),
+            })
+            .collect();
+        let kinds: Vec<SlotKind> = rotors.iter().map(|(_, kind)| *kind).collect();
+        let mut machine = Enigma::new(model, &kinds);
+        for (idx, (name, _)) in rotors.iter().enumerate() {
+            machine.choose_rotor(name, idx as u8)?;
+        }
+        Ok(machine)
+    }
+
+    /// Create a naval M4 enigma configuration with the Beta Greek wheel, rotors I,II,III
+    /// and the thin reflector B, with no wires in the plugboard
+    pub fn m4() -> Self {
+        Self::from_model(model::Model::M4)
+    }
+
+    /// Model of the machine
+    pub fn model(&self) -> model::Model {
+        self.model
+    }
+
+    /// Number of rotors in the machine, including fixed rotors and Greek wheels
+    pub fn rotor_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Kinds of the slots in the machine, from left to right
+    pub fn slot_kinds(&self) -> Vec<SlotKind> {
+        self.slots.iter().map(|slot| slot.kind).collect()
+    }
+
+    /// Names of the rotors in the machine, from left to right
+    pub fn rotor_names(&self) -> Vec<String> {
+        self.slots.iter().map(|slot| slot.name.clone()).collect()
+    }
+
+    /// Names of the rotors in the machine from left to right, with the fixed rotors marked
+    /// by a '*' as in the key string
+    pub(crate) fn marked_rotor_names(&self) -> Vec<String> {
+        self.slots.iter()
+            .map(|slot| match slot.kind {
+                SlotKind::Fixed => format!("{}{}", slot.name, FIXED_ROTOR_MARK),
+                _ => slot.name.clone(),
+            })
+            .collect()
+    }
+
+    /// Ring settings of the rotors as letters, from left to right
+    pub fn rings(&self) -> String {
+        self.slots.iter().map(|slot| slot.rotor.ring()).collect()
+    }
+
+    /// Current positions of the rotors as the letters shown in the windows, from left to right
+    pub fn positions(&self) -> String {
+        self.slots.iter().map(|slot| slot.rotor.position()).collect()
+    }
+
+    /// Name of the reflector in the machine, "D" for the rewirable reflector
+    pub fn reflector_name(&self) -> &str {
+        &self.reflector_name
+    }
+
+    /// Current setting of the reflector
+    pub fn reflector_position(&self) -> char {
+        self.reflector.position()
+    }
+
+    /// Pairs plugged into the rewirable reflector in the form "a-b,c-d,...", or None if
+    /// the reflector comes from the model's catalogue
+    pub fn reflector_wiring(&self) -> Option<String> {
+        if self.reflector_name == model::REWIRABLE_REFLECTOR {
+            Some(self.reflector.pairs())
+        } else {
+            None
+        }
+    }
+
+    /// Wiring of the entry wheel, listing the key wired to each contact in order
+    pub fn entry_wheel_wiring(&self) -> String {
+        self.entry_wheel.configuration()
+    }
+
+    /// Wires in the plugboard in the form "a-b,c-d,..." (empty while the Uhr is attached)
+    pub fn plugboard_wires(&self) -> String {
+        self.plugboard.wires()
+    }
+
+    /// Ordered pairs plugged into the Uhr in the form "a-b,c-d,..." along with its setting,
+    /// or None if the Uhr isn't attached
+    pub fn uhr(&self) -> Option<(String, u8)> {
+        self.plugboard.uhr()
+    }
+
+    /// Key string of the machine, see the `Display` implementation
+    fn key(&self) -> String {
+        let mut tokens: Vec<String> = Vec::new();
+        if self.model != model::Model::EnigmaI {
+            tokens.push(self.model.to_string());
+        }
+        let mut reflector = match self.reflector_wiring() {
+            Some(wiring) => format!("{}:{}", model::REWIRABLE_REFLECTOR,
+                                    wiring.replace('-', "").to_ascii_uppercase()),
+            None => self.reflector_name.clone(),
+        };
+        if self.reflector_position() != 'a' {
+            reflector.push('@');
+            reflector.push(self.reflector_position().to_ascii_uppercase());
+        }
+        tokens.push(reflector);
+        tokens.push(self.marked_rotor_names().join("-"));
+        tokens.push(self.rings().bytes()
+            .map(|ring| format!("{:02}", ring - 96u8))
+            .collect::<Vec<String>>()
+            .join("-"));
+        tokens.push(self.positions().to_ascii_uppercase());
+        if self.entry_wheel != self.model.entry_wheel() {
+            tokens.push(format!("ETW:{}", self.entry_wheel_wiring().to_ascii_uppercase()));
+        }
+        let pairs = match self.uhr() {
+            Some((pairs, setting)) => {
+                tokens.push(format!("UHR:{:02}", setting));
+                pairs
             }
-            "VIII" => {
-                self.rotors[position as usize] = rotor::Rotor::new_viii();
+            None => self.plugboard_wires(),
+        };
+        tokens.extend(pairs.split(',')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| pair.replace('-', "").to_ascii_uppercase()));
+        tokens.join(" ")
+    }
+
+    /// Set a rotor from the model's catalogue in a given position. Positions count from the
+    /// left, Greek wheel slots only accept Greek wheels, which in turn can't be placed in
+    /// any other slot.
+    pub fn choose_rotor(&mut self, rotor: &str, position: u8) -> Result<(), EnigmaError> {
+        let slot = match self.slots.get_mut(position as usize) {
+            Some(slot) => slot,
+            None => { return Err(EnigmaError::InvalidRotorPosition(position.to_string())); }
+        };
+        let new_rotor = match slot.kind {
+            SlotKind::Greek => self.model.greek_wheel(rotor),
+            _ => self.model.rotor(rotor),
+        };
+        match new_rotor {
+            Some(new_rotor) => {
+                let names = match slot.kind {
+                    SlotKind::Greek => self.model.greek_wheel_names(),
+                    _ => self.model.rotor_names(),
+                };
+                slot.name = names.into_iter()
+                    .find(|name| name.eq_ignore_ascii_case(rotor))
+                    .expect("Rotor was found in the catalogue")
+                    .to_string();
+                slot.rotor = new_rotor;
                 Ok(())
             }
-            r => {
-                Err(EnigmaError::InvalidRotor(r.to_string()))
+            None => {
+                Err(EnigmaError::InvalidRotor(rotor.to_string()))
             }
         }
     }
 
-    /// Set a particular reflector
+    /// Set a particular reflector from the model's catalogue, for example "b" on the
+    /// Enigma I or "b-thin" on the M4
     pub fn choose_reflector(&mut self, reflector: &str) -> Result<(), EnigmaError> {
-        match reflector {
-            "a" | "A" => {
-                self.reflector = reflector::Reflector::new_a();
+        match self.model.reflector(reflector) {
+            Some(new_reflector) => {
+                self.reflector_name = self.model.reflector_names().into_iter()
+                    .find(|name| name.eq_ignore_ascii_case(reflector))
+                    .expect("Reflector was found in the catalogue")
+                    .to_string();
+                self.reflector = new_reflector;
                 Ok(())
             }
-            "b" | "B" => {
-                self.reflector = reflector::Reflector::new_b();
+            None => {
+                Err(EnigmaError::InvalidReflector(reflector.to_string()))
+            }
+        }
+    }
+
+    /// Set a particular entry wheel, either the alphabetical military wiring ("military" or
+    /// "abc") or the keyboard ordered wiring of the commercial machines ("qwertzu")
+    pub fn choose_entry_wheel(&mut self, entry_wheel: &str) -> Result<(), EnigmaError> {
+        match entry_wheel.to_ascii_lowercase().as_str() {
+            "military" | "abc" => {
+                self.entry_wheel = entry_wheel::EntryWheel::new_military();
                 Ok(())
             }
-            "c" | "C" => {
-                self.reflector = reflector::Reflector::new_c();
+            "qwertzu" => {
+                self.entry_wheel = entry_wheel::EntryWheel::new_qwertzu();
                 Ok(())
             }
-            r => {
-                Err(EnigmaError::InvalidReflector(r.to_string()))
+            _ => {
+                Err(EnigmaError::InvalidEntryWheel(entry_wheel.to_string()))
             }
         }
     }
 
-    /// Change the rotor settings
+    /// Set a custom entry wheel wiring, listing the key wired to each contact of the wheel
+    /// in order (so "QWERTZUIOASDFGHJKPYXCVBNML" is the commercial entry wheel)
+    pub fn set_entry_wheel_wiring(&mut self, configuration: &str) -> Result<(), EnigmaError> {
+        if !entry_wheel::EntryWheel::is_valid_configuration(configuration) {
+            return Err(EnigmaError::InvalidEntryWheel(configuration.to_string()));
+        }
+        self.entry_wheel = entry_wheel::EntryWheel::new(configuration);
+        Ok(())
+    }
+
+    /// Change the rotor settings, one letter per rotor from the left (including fixed
+    /// rotors and Greek wheels)
     pub fn set_rotors(&mut self, setting: &str) -> Result<(), EnigmaError> {
-        for (idx, c) in setting.chars().enumerate() {
-            if idx < 3 {
-                self.rotors[idx].set(c);
-            } else { break; };
+        if setting.chars().count() != self.rotor_count()
+            || !setting.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(EnigmaError::InvalidRotorSetting(setting.to_string()));
+        }
+        for (slot, c) in self.slots.iter_mut().zip(setting.chars()) {
+            slot.rotor.set(c);
+        }
+        Ok(())
+    }
+
+    /// Change the ring settings (Ringstellung) of the rotors, should be one letter per rotor,
+    /// for example "bul" sets the ring settings of the rotors to B, U, and L (02, 21, 12).
+    pub fn set_rings(&mut self, setting: &str) -> Result<(), EnigmaError> {
+        if setting.chars().count() != self.rotor_count()
+            || !setting.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(EnigmaError::InvalidRingSetting(setting.to_string()));
+        }
+        for (slot, c) in self.slots.iter_mut().zip(setting.chars()) {
+            slot.rotor.set_ring(c);
         }
         Ok(())
     }
 
-    /// Change the reflector setting
+    /// Plug the rewirable reflector (UKW-D), on models which support it. The wiring is a
+    /// string of the form "a-b,c-d,..." with 12 pairs in Bletchley Park notation, where
+    /// J-Y is the fixed pair wired inside the reflector.
+    pub fn rewire_reflector(&mut self, wires: &str) -> Result<(), EnigmaError> {
+        if !self.model.has_rewirable_reflector() {
+            return Err(EnigmaError::InvalidReflector(model::REWIRABLE_REFLECTOR.to_string()));
+        }
+        let mut pairs: Vec<(char, char)> = Vec::new();
+        for wire in wires.split(",") {
+            match Self::parse_wire(wire.trim()) {
+                Some(pair) => pairs.push(pair),
+                None => { return Err(EnigmaError::InvalidReflectorWiring(wires.to_string())); }
+            }
+        }
+        match reflector::Reflector::new_ukw_d(&pairs) {
+            Ok(new_reflector) => {
+                self.reflector_name = model::REWIRABLE_REFLECTOR.to_string();
+                self.reflector = new_reflector;
+                Ok(())
+            }
+            Err(err) => {
+                let reason = match err {
+                    reflector::ReflectorError::InvalidLetter => "invalid letter",
+                    reflector::ReflectorError::OverlappingPairs => "overlapping pairs",
+                    reflector::ReflectorError::FixedPair => "J and Y are fixed",
+                    reflector::ReflectorError::IncompletePairs => "expected 12 pairs",
+                };
+                Err(EnigmaError::InvalidReflectorWiring(format!("{} ({})", wires, reason)))
+            }
+        }
+    }
+
+    /// Change the reflector setting, a single letter
     pub fn set_reflector(&mut self, setting: &str) -> Result<(), EnigmaError> {
-        for (idx, c) in setting.chars().enumerate() {
-            if idx < 1 {
+        let mut letters = setting.chars();
+        match (letters.next(), letters.next()) {
+            (Some(c), None) if c.is_ascii_alphabetic() => {
                 self.reflector.set(c);
-            } else {
-                break;
+                Ok(())
             }
+            _ => Err(EnigmaError::InvalidReflector(setting.to_string())),
         }
-        Ok(())
     }
 
     /// Add a single wire to the plugboard, should be a string of the form "<start>-<end>",
     /// for example "a-e" connects a and e on the plugboard
     pub fn add_plugboard_wire(&mut self, wire: &str) -> Result<(), EnigmaError> {
-        let mut letters: Vec<char> = Vec::new();
-        for s in wire.split("-") {
-            if s.len() == 1 {
-                letters.push(s.chars().next()
-                    .expect("Error in converting length 1 str to char"))
-            } else {
-                return Err(EnigmaError::InvalidPlugboardWire(wire.to_string()));
-            }
-        }
-        if letters.len() != 2 {
-            return Err(EnigmaError::InvalidPlugboardWire(wire.to_string()));
+        if !self.model.has_plugboard() {
+            return Err(EnigmaError::NoPlugboard(self.model.to_string()));
         }
-        match self.plugboard.add_wire(letters[0], letters[1]) {
+        let (start, end) = match Self::parse_wire(wire) {
+            Some(letters) => letters,
+            None => { return Err(EnigmaError::InvalidPlugboardWire(wire.to_string())); }
+        };
+        match self.plugboard.add_wire(start, end) {
             Ok(()) => { Ok(()) }
             Err(err) => {
                 match err {
@@ -159,12 +468,39 @@ impl Enigma {
                         Err(EnigmaError::PlugboardWireOverlap(
                             format!("Couldn't add wire due to overlap {}", wire).to_string()))
                     }
+                    plugboard::PlugboardError::UhrAttached => {
+                        Err(EnigmaError::InvalidUhr(
+                            format!("Couldn't add wire {} while the Uhr is attached", wire)))
+                    }
                     _ => { panic!("Unknown error when inserting plugboard wire!") }
                 }
             }
         }
     }
 
+    /// Turn a pair of letters from the key string ("AB") into a wire ("a-b"), anything but
+    /// two letters is left for the wire parsing to reject
+    fn key_pair(pair: &str) -> String {
+        let letters: Vec<String> = pair.to_ascii_lowercase().chars().map(String::from).collect();
+        letters.join("-")
+    }
+
+    /// Parse a wire of the form "<start>-<end>" into its two letters
+    fn parse_wire(wire: &str) -> Option<(char, char)> {
+        let mut letters: Vec<char> = Vec::new();
+        for s in wire.split("-") {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_alphabetic() => letters.push(c),
+                _ => { return None; }
+            }
+        }
+        if letters.len() != 2 {
+            return None;
+        }
+        Some((letters[0], letters[1]))
+    }
+
     /// Add a set of wires to the plugboard, should be a string of the form
     /// "wire,wire,wire,..." where each wire is a string of the form
     /// "<start>-<end>. For example, the string "a-e,b-d,x-z" will
@@ -178,6 +514,9 @@ impl Enigma {
 
     /// Remove a wire from the plugboard by specifying one of its ends
     pub fn remove_plugboard_wire(&mut self, wire: char) -> Result<(), EnigmaError> {
+        if !self.model.has_plugboard() {
+            return Err(EnigmaError::NoPlugboard(self.model.to_string()));
+        }
         let end = self.plugboard.translate_char(wire);
         match self.plugboard.remove_wire(wire, end) {
             Ok(()) => Ok(()),
@@ -201,20 +540,53 @@ impl Enigma {
         Ok(())
     }
 
+    /// Attach the Enigma-Uhr in place of the plugboard cables. The pairs are a string of the
+    /// form "a-b,c-d,..." with exactly ten ordered pairs, where the first letter of the i-th
+    /// pair takes plug ia and the second plug ib. The setting is the Uhr position (0..40).
+    pub fn attach_uhr(&mut self, pairs: &str, setting: u8) -> Result<(), EnigmaError> {
+        if !self.model.has_plugboard() {
+            return Err(EnigmaError::NoPlugboard(self.model.to_string()));
+        }
+        let mut letters: Vec<(char, char)> = Vec::new();
+        for wire in pairs.split(",") {
+            match Self::parse_wire(wire.trim()) {
+                Some(pair) => letters.push(pair),
+                None => { return Err(EnigmaError::InvalidPlugboardWire(wire.to_string())); }
+            }
+        }
+        self.plugboard.attach_uhr(&letters, setting)
+            .map_err(|err| Self::uhr_error(err, &format!("{} at {:02}", pairs, setting)))
+    }
+
+    /// Turn the attached Uhr to a new setting (0..40)
+    pub fn set_uhr(&mut self, setting: u8) -> Result<(), EnigmaError> {
+        self.plugboard.set_uhr(setting)
+            .map_err(|err| Self::uhr_error(err, &format!("{:02}", setting)))
+    }
+
+    /// Remove the Uhr, leaving a plugboard with no wires
+    pub fn detach_uhr(&mut self) {
+        self.plugboard.detach_uhr();
+    }
+
+    fn uhr_error(err: plugboard::PlugboardError, context: &str) -> EnigmaError {
+        EnigmaError::InvalidUhr(format!("{} ({})", context, err))
+    }
+
     /// Translate a string through the Enigma machine
     pub fn translate(&mut self, input: &str) -> String {
         let mut translated_str: String = String::new();
         for c in input.chars() {
             match c {
                 'a'..='z' => {
-                    translated_str.push(self.translate_char(c));
-                    // Only step if letter actually passing through the machine
+                    // Only step if letter actually passing through the machine, the
+                    // rotors move when the key is pressed, before the lamp lights
                     self.step();
+                    translated_str.push(self.translate_char(c));
                 }
                 'A'..='Z' => {
-                    translated_str.push(self.translate_char(c.to_ascii_lowercase()));
-                    // Only step if letter actually passing through the machine
                     self.step();
+                    translated_str.push(self.translate_char(c.to_ascii_lowercase()));
                 }
                 other => { translated_str.push(other); }
             }
@@ -222,60 +594,268 @@ impl Enigma {
         translated_str
     }
 
+    /// Press the key of a letter (a-z, either case): the rotors move, then the current flows
+    /// through the machine and lights a lamp. Use `positions` to read the windows without
+    /// pressing anything.
+    pub fn press_key(&mut self, key: char) -> Result<Keystroke, EnigmaError> {
+        if !key.is_ascii_alphabetic() {
+            return Err(EnigmaError::InvalidCharacter(format!("{:?} (no such key)", key)));
+        }
+        self.step();
+        Ok(Keystroke {
+            lamp: self.translate_char(key.to_ascii_lowercase()),
+            windows: self.positions(),
+            reflector_position: self.reflector_position(),
+        })
+    }
+
     /// Pass a single character through the engima machine
     fn translate_char(&self, input: char) -> char {
         let mut transfer_char: char = input;
-        // Pass through plugboard
+        // Pass through plugboard and entry wheel
         transfer_char = self.plugboard.translate_char(transfer_char);
-        // Forward pass through rotors
-        for r in self.rotors.iter() {
-            transfer_char = r.translate_forward(transfer_char);
+        transfer_char = self.entry_wheel.translate_forward(transfer_char);
+        // Forward pass through rotors, entering at the rightmost rotor
+        for slot in self.slots.iter().rev() {
+            transfer_char = slot.rotor.translate_forward(transfer_char);
         }
         // Through the reflector
         transfer_char = self.reflector.translate(transfer_char);
         // Reverse pass through the rotors
-        for r in self.rotors.iter().rev() {
-            transfer_char = r.translate_reverse(transfer_char);
+        for slot in self.slots.iter() {
+            transfer_char = slot.rotor.translate_reverse(transfer_char);
         }
-        // Back through the plugboard
-        transfer_char = self.plugboard.translate_char(transfer_char);
+        // Back through the entry wheel and plugboard
+        transfer_char = self.entry_wheel.translate_reverse(transfer_char);
+        transfer_char = self.plugboard.translate_char_reverse(transfer_char);
         transfer_char
     }
 
-    /// Step the rotors of the enigma machine
+    /// Step the rotors of the enigma machine, using the mechanism of the machine's model.
+    /// Fixed rotors and Greek wheels are never driven.
     fn step(&mut self) {
-        let mut to_step: bool = true;
-        for r in self.rotors.iter_mut().rev() {
-            if to_step {
-                to_step = r.step();
+        let stepping: Vec<usize> = self.slots.iter().enumerate()
+            .filter(|(_, slot)| slot.kind == SlotKind::Stepping)
+            .map(|(idx, _)| idx)
+            .collect();
+        match self.model.step_mechanism() {
+            model::StepMechanism::Pawl => self.step_pawls(&stepping),
+            model::StepMechanism::Cog => self.step_cogs(&stepping),
+        }
+    }
+
+    /// Step the stepping rotors at the given slots with pawls and ratchets.
+    ///
+    /// Each stepping rotor other than the rightmost has a pawl resting on the notch ring of
+    /// the next stepping rotor to its right. When that neighbor is at a notch, the pawl
+    /// engages and pushes both rotors forward, so a middle rotor sitting on its own notch
+    /// steps twice in a row (the double step). The rightmost stepping rotor is always stepped
+    /// by its own pawl.
+    fn step_pawls(&mut self, stepping: &[usize]) {
+        let Some(&rightmost) = stepping.last() else { return; };
+        // Decide which pawls engage before moving anything
+        let mut to_step: Vec<bool> = vec![false; self.slots.len()];
+        to_step[rightmost] = true;
+        for pair in stepping.windows(2) {
+            if self.slots[pair[1]].rotor.at_notch() {
+                to_step[pair[0]] = true;
+                to_step[pair[1]] = true;
+            }
+        }
+        for (slot, step) in self.slots.iter_mut().zip(to_step) {
+            if step {
+                slot.rotor.step();
+            }
+        }
+    }
+
+    /// Step the stepping rotors at the given slots with the gear drive of the Enigma G.
+    ///
+    /// The rightmost rotor always steps, and any rotor which moves off one of its notches
+    /// carries the movement to its left neighbor, like an odometer (so there is no double
+    /// step). When the leftmost rotor carries, the reflector steps as well.
+    fn step_cogs(&mut self, stepping: &[usize]) {
+        let mut carry = true;
+        for &idx in stepping.iter().rev() {
+            if !carry {
+                break;
+            }
+            let rotor = &mut self.slots[idx].rotor;
+            carry = rotor.at_notch();
+            rotor.step();
+        }
+        if carry && self.model.reflector_steps() {
+            self.reflector.step();
+        }
+    }
+}
+
+impl FromStr for Enigma {
+    type Err = EnigmaError;
+
+    /// Parse a machine from its key string, see the `Display` implementation
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = s.split_whitespace().peekable();
+        let missing = |part: &str| EnigmaError::InvalidKey(format!("{} (missing {})", s, part));
+        let mut model = model::Model::EnigmaI;
+        if let Some(parsed) = tokens.peek().and_then(|token| token.parse::<model::Model>().ok()) {
+            model = parsed;
+            tokens.next();
+        }
+        let reflector = tokens.next().ok_or_else(|| missing("reflector"))?;
+        let rotors = tokens.next().ok_or_else(|| missing("rotors"))?;
+        let rings = tokens.next().ok_or_else(|| missing("ring settings"))?;
+        let positions = tokens.next().ok_or_else(|| missing("rotor positions"))?;
+
+        // Rotors, with the kind of each slot taken from the names
+        let names: Vec<&str> = rotors.split('-').collect();
+        let mut machine = Enigma::from_rotor_names(model, &names)?;
+
+        // Reflector, optionally followed by its position
+        let (reflector, position) = match reflector.split_once('@') {
+            Some((reflector, position)) => (reflector, Some(position)),
+            None => (reflector, None),
+        };
+        match reflector.split_once(':') {
+            Some((name, wiring)) if name.eq_ignore_ascii_case(model::REWIRABLE_REFLECTOR) => {
+                let wires: Vec<String> = wiring.split(',').map(Self::key_pair).collect();
+                machine.rewire_reflector(&wires.join(","))?;
             }
+            _ => machine.choose_reflector(reflector)?,
         }
+        if let Some(position) = position {
+            let mut letters = position.chars();
+            match (letters.next(), letters.next()) {
+                (Some(c), None) if c.is_ascii_alphabetic() => machine.set_reflector(position)?,
+                _ => {
+                    return Err(EnigmaError::InvalidReflector(
+                        format!("{} (position must be a letter)", position)));
+                }
+            }
+        }
+
+        // Ring settings as numbers 01-26, and positions as letters
+        let ring_letters: String = rings.split('-')
+            .map(|ring| match ring.parse::<u8>() {
+                Ok(n) if (1..=26).contains(&n) && ring.len() == 2 => Ok((n + 96u8) as char),
+                _ => Err(EnigmaError::InvalidRingSetting(format!("{} (expected 01-26)", ring))),
+            })
+            .collect::<Result<String, EnigmaError>>()?;
+        if ring_letters.len() != machine.rotor_count() {
+            return Err(EnigmaError::InvalidRingSetting(
+                format!("{} (expected {} rings)", rings, machine.rotor_count())));
+        }
+        machine.set_rings(&ring_letters)?;
+        if positions.chars().count() != machine.rotor_count()
+            || !positions.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(EnigmaError::InvalidRotorSetting(
+                format!("{} (expected {} letters)", positions, machine.rotor_count())));
+        }
+        machine.set_rotors(positions)?;
+
+        // Entry wheel, Uhr, and plugboard pairs
+        if let Some(wiring) = tokens.peek().and_then(|token| token.strip_prefix("ETW:")) {
+            machine.set_entry_wheel_wiring(wiring)?;
+            tokens.next();
+        }
+        let uhr = match tokens.peek().and_then(|token| token.strip_prefix("UHR:")) {
+            Some(setting) => match setting.parse::<u8>() {
+                Ok(setting) => Some(setting),
+                Err(_) => {
+                    return Err(EnigmaError::InvalidUhr(format!("{} (setting must be 00-39)", setting)));
+                }
+            },
+            None => None,
+        };
+        if uhr.is_some() {
+            tokens.next();
+        }
+        let mut pairs: Vec<String> = Vec::new();
+        for pair in tokens {
+            let mut letters = pair.chars();
+            match (letters.next(), letters.next(), letters.next()) {
+                (Some(a), Some(b), None) if a.is_ascii_alphabetic() && b.is_ascii_alphabetic()
+                    && !a.eq_ignore_ascii_case(&b) => {
+                    pairs.push(Self::key_pair(pair));
+                }
+                _ => {
+                    return Err(EnigmaError::InvalidKey(format!("{} (unexpected {})", s, pair)));
+                }
+            }
+        }
+        match uhr {
+            Some(setting) => machine.attach_uhr(&pairs.join(","), setting)?,
+            None if !pairs.is_empty() => machine.add_plugboard_wires(&pairs.join(","))?,
+            None => {}
+        }
+        Ok(machine)
+    }
+}
+
+impl RotorMachine for Enigma {
+    fn name(&self) -> String {
+        format!("Enigma {}", self.model)
+    }
+
+    fn press(&mut self, letter: char) -> char {
+        Enigma::step(self);
+        self.translate_char(letter)
+    }
+
+    fn step(&mut self) {
+        Enigma::step(self);
+    }
+
+    fn state(&self) -> String {
+        self.positions()
     }
 }
 
 pub enum EnigmaError {
+    InvalidModel(String),
     InvalidRotor(String),
+    InvalidRotorPosition(String),
     InvalidReflector(String),
+    InvalidReflectorWiring(String),
+    InvalidRotorSetting(String),
+    InvalidRingSetting(String),
+    InvalidEntryWheel(String),
     InvalidPlugboardWire(String),
     PlugboardWireOverlap(String),
     PlugboardWireRemoveFailure(String),
+    NoPlugboard(String),
+    InvalidUhr(String),
+    InvalidConfiguration(String),
+    InvalidKey(String),
+    InvalidCharacter(String),
 }
 
 impl fmt::Display for EnigmaError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            EnigmaError::InvalidModel(s) => { write!(f, "Invalid Model: {}", s) }
             EnigmaError::InvalidRotor(s) => { write!(f, "Invalid Rotor: {}", s) }
+            EnigmaError::InvalidRotorPosition(s) => { write!(f, "Invalid Rotor Position: {}", s) }
             EnigmaError::InvalidReflector(s) => { write!(f, "Invalid Reflector: {}", s) }
+            EnigmaError::InvalidReflectorWiring(s) => { write!(f, "Invalid Reflector Wiring: {}", s) }
+            EnigmaError::InvalidRotorSetting(s) => { write!(f, "Invalid Rotor Setting: {}", s) }
+            EnigmaError::InvalidRingSetting(s) => { write!(f, "Invalid Ring Setting: {}", s) }
+            EnigmaError::InvalidEntryWheel(s) => { write!(f, "Invalid Entry Wheel: {}", s) }
             EnigmaError::InvalidPlugboardWire(s) => { write!(f, "Invalid Plugboard Wire: {}", s) }
             EnigmaError::PlugboardWireOverlap(s) => { write!(f, "Plugboard Wire Overlap: {}", s) }
             EnigmaError::PlugboardWireRemoveFailure(s) => { write!(f, "Plugboard Wire Removal Failure: {}", s) }
+            EnigmaError::NoPlugboard(s) => { write!(f, "Model has no Plugboard: {}", s) }
+            EnigmaError::InvalidUhr(s) => { write!(f, "Invalid Uhr: {}", s) }
+            EnigmaError::InvalidConfiguration(s) => { write!(f, "Invalid Configuration: {}", s) }
+            EnigmaError::InvalidKey(s) => { write!(f, "Invalid Key: {}", s) }
+            EnigmaError::InvalidCharacter(s) => { write!(f, "Invalid Character: {}", s) }
         }
     }
 }
 
 #[cfg(test)]
 mod test_enigma {
-    use crate::machine::enigma::Enigma;
+    use crate::machine::enigma::{Enigma, SlotKind};
 
     #[test]
     fn test_translation() {
@@ -288,7 +868,41 @@ mod test_enigma {
         assert_ne!(encoded, decoded);
         assert_ne!("aaaa", encoded);
         assert_eq!("aaaa", decoded);
-        assert_eq!("nqxh", encoded);
+        assert_eq!("bdzg", encoded);
+    }
+
+    #[test]
+    fn test_double_step() {
+        // Reference sequence for rotors I-II-III, with the middle rotor stepping twice
+        let mut test_machine = Enigma::default();
+        _ = test_machine.set_rotors("adu");
+        let mut windows: Vec<String> = Vec::new();
+        for _ in 0..4 {
+            test_machine.step();
+            windows.push(window_letters(&test_machine));
+        }
+        assert_eq!(vec!["adv", "aew", "bfx", "bfy"], windows);
+        // Rotor and reflector settings need exactly one letter per rotor and a single letter
+        assert!(test_machine.set_rotors("a1b").is_err());
+        assert!(test_machine.set_rotors("ab").is_err());
+        assert!(test_machine.set_reflector("1").is_err());
+        assert!(test_machine.set_reflector("ab").is_err());
+        assert_eq!("bfy", window_letters(&test_machine));
+    }
+
+    #[test]
+    fn test_reference_message() {
+        // Example message from the Enigma I operating manual (1930)
+        let mut test_machine = Enigma::default();
+        _ = test_machine.choose_rotor("II", 0);
+        _ = test_machine.choose_rotor("I", 1);
+        _ = test_machine.choose_rotor("III", 2);
+        _ = test_machine.choose_reflector("A");
+        _ = test_machine.set_rings("xmv");
+        _ = test_machine.add_plugboard_wires("a-m,f-i,n-v,p-s,t-u,w-z");
+        _ = test_machine.set_rotors("abl");
+        assert_eq!("feindliqeinfanteriekolonnebeobaqtetxanfangsuedausgangbaerwaldexendedreikmostwaertsneustadt",
+                   test_machine.translate("GCDSEAHUGWTQGRKVLFGXUCALXVYMIGMMNMFDXTGNVHVRMMEVOUYFZSLRHDRRXFJWCFHUHMUNZEFRDISIKBGPMYVXUZ"));
     }
 
     #[test]
@@ -306,6 +920,228 @@ mod test_enigma {
         assert_ne!(encoded, "this is a coded message");
     }
 
+    #[test]
+    fn test_ring_settings() {
+        let mut test_encoder = Enigma::default();
+        _ = test_encoder.choose_reflector("B");
+        assert!(test_encoder.set_rings("bbb").is_ok());
+        let mut test_decoder = test_encoder.clone();
+        let encoded = test_encoder.translate("aaaa");
+        assert_eq!("ewty", encoded);
+        assert_eq!("aaaa", test_decoder.translate(&encoded));
+        // Ring settings need exactly one letter per rotor
+        assert!(test_encoder.set_rings("bb").is_err());
+        assert!(test_encoder.set_rings("b1b").is_err());
+    }
+
+    #[test]
+    fn test_m4_reference_message() {
+        // Message sent by U-534 on 1 May 1945
+        let mut test_machine = Enigma::m4();
+        _ = test_machine.choose_rotor("Beta", 0);
+        _ = test_machine.choose_rotor("II", 1);
+        _ = test_machine.choose_rotor("IV", 2);
+        _ = test_machine.choose_rotor("I", 3);
+        _ = test_machine.choose_reflector("B-thin");
+        _ = test_machine.set_rings("aaav");
+        _ = test_machine.add_plugboard_wires("a-t,b-l,d-f,g-j,h-m,n-w,o-p,q-y,r-z,v-x");
+        _ = test_machine.set_rotors("vjna");
+        assert_eq!("vonvonjlooksjhffttteinseinsdreizwoyyqnnsneuninhaltxxbeiangriffunterwassergedruecktywabosxletztergegnerstandnulachtdreinuluhrmarquantonjotaneunachtseyhsdreiyzwozwonulgradyachtsmystossenachxeknsviermbfaelltynnnnnnooovierysichteinsnull",
+                   test_machine.translate("NCZWVUSXPNYMINHZXMQXSFWXWLKJAHSHNMCOCCAKUQPMKCSMHKSEINJUSBLKIOSXCKUBHMLLXCSJUSRRDVKOHULXWCCBGVLIYXEOAHXRHKKFVDREWEZLXOBAFGYUJQUKGRTVUKAMEURBVEKSUHHVOYHABCJWMAKLFKLMYFVNRIZRVVRTKOFDANJMOLBGFFLEOPRGTFLVRHOWOPBEKVWMUQFMPWPARMFHAGKXIIBG"));
+    }
+
+    #[test]
+    fn test_m4_compatibility() {
+        // With Beta at A and thin reflector B, the M4 is equivalent to an M3 with reflector B
+        let mut m4_machine = Enigma::m4();
+        let mut m3_machine = Enigma::default();
+        _ = m3_machine.choose_reflector("B");
+        assert_eq!(m3_machine.translate("thisisatest"), m4_machine.translate("thisisatest"));
+        // Thin and wide reflectors and the Greek wheels are not interchangeable
+        assert!(m4_machine.choose_reflector("B").is_err());
+        assert!(m4_machine.choose_rotor("I", 0).is_err());
+        assert!(m3_machine.choose_reflector("B-thin").is_err());
+        assert!(m3_machine.choose_rotor("Beta", 0).is_err());
+    }
+
+    #[test]
+    fn test_variable_rotors() {
+        use crate::machine::enigma::SlotKind;
+        let mut test_encoder = Enigma::with_slots(&[SlotKind::Fixed, SlotKind::Stepping,
+            SlotKind::Stepping, SlotKind::Stepping, SlotKind::Stepping, SlotKind::Stepping]);
+        assert_eq!(6, test_encoder.rotor_count());
+        _ = test_encoder.set_rotors("qevjzq");
+        let mut test_decoder = test_encoder.clone();
+        let to_encode = "a machine with more rotors still decodes its own messages".repeat(20);
+        let encoded = test_encoder.translate(&to_encode);
+        assert_eq!(to_encode, test_decoder.translate(&encoded));
+        // The fixed rotor never moves, while the stepping rotors have all moved
+        let windows: String = window_letters(&test_encoder);
+        assert_eq!('q', windows.chars().next().unwrap());
+        assert!(windows.chars().skip(1).zip("evjzq".chars()).all(|(a, b)| a != b));
+    }
+
+    #[test]
+    fn test_entry_wheel() {
+        let mut test_encoder = Enigma::default();
+        _ = test_encoder.choose_reflector("B");
+        assert!(test_encoder.choose_entry_wheel("qwertzu").is_ok());
+        let mut test_decoder = test_encoder.clone();
+        let encoded = test_encoder.translate("aaaa");
+        assert_ne!("bdzg", encoded);
+        assert_eq!("aaaa", test_decoder.translate(&encoded));
+        // A custom wiring equal to the military one gives the usual result
+        let mut test_machine = Enigma::default();
+        _ = test_machine.choose_reflector("B");
+        assert!(test_machine.set_entry_wheel_wiring("abcdefghijklmnopqrstuvwxyz").is_ok());
+        assert_eq!("bdzg", test_machine.translate("aaaa"));
+        assert!(test_machine.set_entry_wheel_wiring("abc").is_err());
+        assert!(test_machine.choose_entry_wheel("azerty").is_err());
+    }
+
+    #[test]
+    fn test_commercial_models() {
+        use crate::machine::model::Model;
+        let mut test_encoder = Enigma::from_model(Model::K);
+        // Rotor names resolve against the catalogue of the selected model
+        assert!(test_encoder.choose_rotor("III", 0).is_ok());
+        assert!(test_encoder.choose_rotor("VI", 1).is_err());
+        assert!(test_encoder.add_plugboard_wires("a-b").is_err());
+        _ = test_encoder.set_reflector("q");
+        let mut test_decoder = test_encoder.clone();
+        let mut test_military = Enigma::default();
+        _ = test_military.choose_rotor("III", 0);
+        _ = test_military.set_reflector("q");
+        let encoded = test_encoder.translate("commercial machines");
+        assert_ne!(test_military.translate("commercial machines"), encoded);
+        assert_eq!("commercial machines", test_decoder.translate(&encoded));
+        // The Swiss K has its own rotor wirings
+        let mut test_swiss = Enigma::from_model(Model::SwissK);
+        _ = test_swiss.choose_rotor("III", 0);
+        _ = test_swiss.set_reflector("q");
+        assert_ne!(encoded, test_swiss.translate("commercial machines"));
+    }
+
+    #[test]
+    fn test_cog_stepping() {
+        use crate::machine::model::Model;
+        let mut test_machine = Enigma::from_model(Model::G312);
+        // Rotor I, II and III all have notches at U, S and A
+        _ = test_machine.set_rotors("usa");
+        test_machine.step();
+        assert_eq!("vtb", window_letters(&test_machine));
+        assert_eq!('b', test_machine.reflector.position());
+        // B is not a notch of rotor III, so only the right rotor moves, and there is no
+        // double step when the middle rotor sits on a notch (rotor II at C)
+        _ = test_machine.set_rotors("aab");
+        test_machine.step();
+        assert_eq!("aac", window_letters(&test_machine));
+        _ = test_machine.set_rotors("acb");
+        test_machine.step();
+        assert_eq!("acc", window_letters(&test_machine));
+
+        let mut test_encoder = Enigma::from_model(Model::G312);
+        _ = test_encoder.set_reflector("k");
+        let mut test_decoder = test_encoder.clone();
+        let to_encode = "the abwehr machine also turns its reflector".repeat(30);
+        let encoded = test_encoder.translate(&to_encode);
+        assert_eq!(to_encode, test_decoder.translate(&encoded));
+    }
+
+    #[test]
+    fn test_railway_and_t_models() {
+        use crate::machine::model::Model;
+        // Each of the five notches of Enigma T rotor III (W, Z, E, K, Q), the rightmost by
+        // default, turns the middle rotor
+        let mut test_machine = Enigma::from_model(Model::T);
+        for notch in ['w', 'z', 'e', 'k', 'q'] {
+            _ = test_machine.set_rotors(&format!("aa{}", notch));
+            test_machine.step();
+            assert_eq!('b', window_letters(&test_machine).chars().nth(1).unwrap());
+        }
+        _ = test_machine.set_rotors("aaa");
+        test_machine.step();
+        assert_eq!("aab", window_letters(&test_machine));
+
+        for model in [Model::Railway, Model::T] {
+            let mut test_encoder = Enigma::from_model(model);
+            assert!(test_encoder.add_plugboard_wires("a-b").is_err());
+            _ = test_encoder.set_reflector("m");
+            let mut test_decoder = test_encoder.clone();
+            let to_encode = "the reflector can be set but never moves".repeat(30);
+            let encoded = test_encoder.translate(&to_encode);
+            assert_eq!('m', test_encoder.reflector.position());
+            assert_eq!(to_encode, test_decoder.translate(&encoded));
+        }
+        assert_ne!(Enigma::from_model(Model::Railway).translate("railway"),
+                   Enigma::from_model(Model::K).translate("railway"));
+
+        // Reference ciphertexts from a separate simulator written from the published wirings,
+        // the T message double steps its middle rotor on the first key
+        let mut railway: Enigma = "Railway UKW@M III-I-II 03-07-11 QEV".parse().ok().unwrap();
+        assert_eq!("rjfzjgzabkbzrjpxeupohsxviakxxrpedwzsttpzsfjskxjufrizstfrqyzxzh",
+                   railway.translate("dasistdiereichsbahnmitderwettermeldungfuerdiestreckenachberlin"));
+        let mut tirpitz: Enigma = "T UKW@H VII-I-IV 05-26-17 XWK".parse().ok().unwrap();
+        assert_eq!("uomqiacfzldfdpotitkhnektmj", tirpitz.translate("strenggeheimkreuzertirpitz"));
+    }
+
+    #[test]
+    fn test_ukw_d() {
+        let wiring = "a-c,b-o,d-i,e-g,f-h,k-q,l-x,m-r,n-s,p-w,t-v,u-z";
+        let mut test_encoder = Enigma::default();
+        assert!(test_encoder.rewire_reflector(wiring).is_ok());
+        assert!(format!("{:#}", test_encoder).contains("D: "));
+        assert!(format!("{:#}", test_encoder).contains("Pairs: a-c,b-o"));
+        let mut test_decoder = test_encoder.clone();
+        let encoded = test_encoder.translate("rewired reflector");
+        assert_eq!("rewired reflector", test_decoder.translate(&encoded));
+        assert!(test_encoder.rewire_reflector("a-c,b-o").is_err());
+        assert!(test_encoder.rewire_reflector("a-c,b-1").is_err());
+        assert!(Enigma::m4().rewire_reflector(wiring).is_err());
+    }
+
+    #[test]
+    fn test_uhr() {
+        let pairs = "a-b,c-d,e-f,g-h,i-j,k-l,m-n,o-p,q-r,s-t";
+        let mut test_cables = Enigma::default();
+        _ = test_cables.add_plugboard_wires(pairs);
+        let mut test_encoder = Enigma::default();
+        assert!(test_encoder.attach_uhr(pairs, 0).is_ok());
+        assert!(format!("{:#}", test_encoder).contains("Uhr 00: a-b,c-d"));
+        // At setting 00 the Uhr gives the same result as the cables
+        let cable_encoded = test_cables.translate("the uhr switch");
+        assert_eq!(cable_encoded, test_encoder.clone().translate("the uhr switch"));
+        assert!(test_encoder.set_uhr(13).is_ok());
+        let mut test_decoder = test_encoder.clone();
+        let encoded = test_encoder.translate("the uhr switch");
+        assert_ne!(cable_encoded, encoded);
+        assert_eq!("the uhr switch", test_decoder.translate(&encoded));
+        assert!(test_encoder.add_plugboard_wire("x-z").is_err());
+        assert!(test_encoder.set_uhr(40).is_err());
+        assert!(test_encoder.attach_uhr("a-b,c-d", 0).is_err());
+    }
+
+    #[test]
+    fn test_press_key() {
+        let mut test_machine = Enigma::default();
+        _ = test_machine.set_rotors("adu");
+        let mut test_translator = test_machine.clone();
+        let keystroke = test_machine.press_key('H').ok().unwrap();
+        assert_eq!(test_translator.translate("h").chars().next(), Some(keystroke.lamp));
+        assert_eq!("adv", keystroke.windows);
+        assert_eq!('a', keystroke.reflector_position);
+        // Reading the windows doesn't move anything, the next key press double steps
+        assert_eq!("adv", test_machine.positions());
+        assert_eq!("aew", test_machine.press_key('e').ok().unwrap().windows);
+        assert_eq!("bfx", test_machine.press_key('l').ok().unwrap().windows);
+        assert!(test_machine.press_key('1').is_err());
+        assert_eq!("bfx", test_machine.positions());
+    }
+
+    fn window_letters(machine: &Enigma) -> String {
+        machine.slots.iter().map(|slot| slot.rotor.position()).collect()
+    }
+
     #[test]
     fn test_long_translation(){
         let mut test_encoder  = Enigma::default();
@@ -318,4 +1154,55 @@ mod test_enigma {
         assert_eq!(to_encode, decoded);
         assert_ne!(to_encode, encoded);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_key_string() {
+        let mut machine = Enigma::default();
+        _ = machine.choose_rotor("III", 0);
+        _ = machine.choose_rotor("I", 2);
+        _ = machine.choose_reflector("b");
+        _ = machine.add_plugboard_wires("a-b,c-d,e-f");
+        assert_eq!("B III-II-I 01-01-01 AAA AB CD EF", machine.to_string());
+        assert_eq!(machine, "B III-II-I 01-01-01 AAA AB CD EF".parse::<Enigma>().ok().unwrap());
+
+        let mut machines: Vec<Enigma> = Vec::new();
+        let mut m4 = Enigma::m4();
+        _ = m4.choose_rotor("gamma", 0);
+        _ = m4.set_rings("aabz");
+        _ = m4.set_rotors("vjna");
+        machines.push(m4);
+        let mut g312 = Enigma::from_model(crate::machine::model::Model::G312);
+        g312.translate("stepping the reflector");
+        machines.push(g312);
+        let mut ukw_d = Enigma::default();
+        _ = ukw_d.rewire_reflector("a-c,b-o,d-i,e-m,f-g,h-r,k-z,l-s,n-u,p-t,q-w,v-x");
+        _ = ukw_d.attach_uhr("a-b,c-d,e-f,g-h,i-j,k-l,m-n,o-p,q-r,s-t", 27);
+        _ = ukw_d.choose_entry_wheel("qwertzu");
+        machines.push(ukw_d);
+        machines.push(Enigma::with_slots(&[SlotKind::Fixed, SlotKind::Stepping,
+            SlotKind::Stepping, SlotKind::Stepping]));
+        for machine in machines {
+            assert_eq!(machine, machine.to_string().parse::<Enigma>().ok().unwrap());
+        }
+        assert_eq!("G312 UKW@B I-II-III 01-01-01 AAB",
+                   "G312 UKW@B I-II-III 01-01-01 AAB".parse::<Enigma>().ok().unwrap().to_string());
+        assert_eq!("A I*-II-III-IV 01-01-01-01 AAAA",
+                   Enigma::with_slots(&[SlotKind::Fixed, SlotKind::Stepping, SlotKind::Stepping,
+                       SlotKind::Stepping]).to_string());
+    }
+
+    #[test]
+    fn test_key_string_errors() {
+        let error = |key: &str| key.parse::<Enigma>().err().unwrap().to_string();
+        assert_eq!("Invalid Key: B I-II-III (missing ring settings)", error("B I-II-III"));
+        assert_eq!("Invalid Rotor: IX", error("B I-II-IX 01-01-01 AAA"));
+        assert_eq!("Invalid Reflector: B-thin", error("B-thin I-II-III 01-01-01 AAA"));
+        assert_eq!("Invalid Ring Setting: 27 (expected 01-26)", error("B I-II-III 01-27-01 AAA"));
+        assert_eq!("Invalid Ring Setting: 01-01 (expected 3 rings)", error("B I-II-III 01-01 AAA"));
+        assert_eq!("Invalid Rotor Setting: AA1 (expected 3 letters)", error("B I-II-III 01-01-01 AA1"));
+        assert_eq!("Invalid Key: B I-II-III 01-01-01 AAA AB C (unexpected C)",
+                   error("B I-II-III 01-01-01 AAA AB C"));
+        assert_eq!("Model has no Plugboard: K", error("K UKW I-II-III 01-01-01 AAA AB"));
+        assert!(error("B I-II-III 01-01-01 AAA AB BC").starts_with("Plugboard Wire Overlap"));
+    }
+}
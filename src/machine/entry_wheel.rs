@@ -0,0 +1,101 @@
+use std::fmt;
+
+/// Represents the entry wheel (Eintrittswalze), the fixed wheel which connects the
+/// plugboard (or keyboard) to the contacts of the rightmost rotor
+#[derive(Clone, PartialEq, Debug)]
+pub struct EntryWheel {
+    /// Which contact each key is wired to when going forward
+    path_fwd: [u8; 26],
+    /// Which key each contact is wired to when going in reverse (return leg)
+    path_rev: [u8; 26],
+}
+
+impl fmt::Display for EntryWheel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Wire Configuration: {}", self.configuration())
+    }
+}
+
+impl EntryWheel {
+    /// Create a new entry wheel. The configuration lists the key wired to each contact
+    /// of the wheel in order, so "QWERTZUIO..." wires Q to contact A, W to contact B, and so on.
+    pub fn new(configuration: &str) -> Self {
+        let path_rev: [u8; 26] = configuration.to_ascii_lowercase()
+            .bytes().map(|x| x-97u8).collect::<Vec<u8>>().try_into()
+            .unwrap_or_else(
+                |v: Vec<u8>| panic!("Expected a Vec of length 26 but it was {}", v.len()));
+        let mut path_fwd: [u8; 26] = [0; 26];
+        for (i, rev) in path_rev.iter().enumerate() {
+            path_fwd[*rev as usize] = i as u8;
+        }
+        Self {
+            path_fwd,
+            path_rev,
+        }
+    }
+
+    /// Check that a configuration is a valid entry wheel wiring, i.e. that it contains
+    /// each letter exactly once
+    pub fn is_valid_configuration(configuration: &str) -> bool {
+        let mut letters: Vec<char> = configuration.to_ascii_lowercase().chars().collect();
+        letters.sort();
+        letters.into_iter().eq('a'..='z')
+    }
+
+    /// Wiring of the entry wheel in the form accepted by `new`, listing the key wired to
+    /// each contact in order
+    pub fn configuration(&self) -> String {
+        self.path_rev.iter().map(|v| (v+97u8) as char).collect()
+    }
+
+    /// Translate a character from the plugboard to the rotors
+    pub fn translate_forward(&self, input: char) -> char {
+        (self.path_fwd[(input as u8 - 97u8) as usize] + 97u8) as char
+    }
+
+    /// Translate a character from the rotors back to the plugboard
+    pub fn translate_reverse(&self, input: char) -> char {
+        (self.path_rev[(input as u8 - 97u8) as usize] + 97u8) as char
+    }
+
+    /// Entry wheel of the military machines, wired in alphabetical order
+    pub fn new_military() -> Self {
+        Self::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ")
+    }
+
+    /// Entry wheel of the commercial and Railway machines, wired in keyboard order
+    pub fn new_qwertzu() -> Self {
+        Self::new("QWERTZUIOASDFGHJKPYXCVBNML")
+    }
+
+    /// Entry wheel of the Enigma T, with its own wiring
+    pub fn new_t() -> Self {
+        Self::new("KZROUQHYAIGBLWVSTDXFPNMCJE")
+    }
+}
+
+#[cfg(test)]
+mod test_entry_wheel {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        let test_wheel = EntryWheel::new_military();
+        assert_eq!('a', test_wheel.translate_forward('a'));
+        assert_eq!('z', test_wheel.translate_reverse('z'));
+        let test_wheel = EntryWheel::new_qwertzu();
+        assert_eq!('a', test_wheel.translate_forward('q'));
+        assert_eq!('b', test_wheel.translate_forward('w'));
+        assert_eq!('q', test_wheel.translate_reverse('a'));
+        for c in 'a'..='z' {
+            assert_eq!(c, test_wheel.translate_reverse(test_wheel.translate_forward(c)));
+        }
+    }
+
+    #[test]
+    fn test_valid_configuration() {
+        assert!(EntryWheel::is_valid_configuration("QWERTZUIOASDFGHJKPYXCVBNML"));
+        assert!(!EntryWheel::is_valid_configuration("QWERTZUIOASDFGHJKPYXCVBNMQ"));
+        assert!(!EntryWheel::is_valid_configuration("QWERTZ"));
+    }
+}
@@ -1,4 +1,11 @@
 mod plugboard;
-mod rotor;
-mod reflector;
-pub mod enigma;
\ No newline at end of file
+pub(crate) mod rotor;
+pub(crate) mod reflector;
+mod entry_wheel;
+pub mod model;
+pub mod rotor_machine;
+pub mod text;
+pub mod enigma;
+pub mod typex;
+pub mod config;
+pub mod stream;
\ No newline at end of file
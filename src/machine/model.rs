@@ -0,0 +1,287 @@
+use crate::machine::enigma::{EnigmaError, SlotKind};
+use crate::machine::entry_wheel::EntryWheel;
+use crate::machine::reflector::Reflector;
+use crate::machine::rotor::Rotor;
+use std::fmt;
+use std::str::FromStr;
+
+/// Catalogue of named rotors (or reflectors) available for a model
+type Catalogue<T> = &'static [(&'static str, fn() -> T)];
+
+const MILITARY_ROTORS: Catalogue<Rotor> = &[
+    ("I", Rotor::new_i), ("II", Rotor::new_ii), ("III", Rotor::new_iii),
+    ("IV", Rotor::new_iv), ("V", Rotor::new_v), ("VI", Rotor::new_vi),
+    ("VII", Rotor::new_vii), ("VIII", Rotor::new_viii),
+];
+
+const GREEK_WHEELS: Catalogue<Rotor> = &[("Beta", Rotor::new_beta), ("Gamma", Rotor::new_gamma)];
+
+const COMMERCIAL_ROTORS: Catalogue<Rotor> = &[
+    ("I", Rotor::new_k_i), ("II", Rotor::new_k_ii), ("III", Rotor::new_k_iii),
+];
+
+const SWISS_K_ROTORS: Catalogue<Rotor> = &[
+    ("I", Rotor::new_swiss_k_i), ("II", Rotor::new_swiss_k_ii), ("III", Rotor::new_swiss_k_iii),
+];
+
+const G312_ROTORS: Catalogue<Rotor> = &[
+    ("I", Rotor::new_g312_i), ("II", Rotor::new_g312_ii), ("III", Rotor::new_g312_iii),
+];
+
+const G260_ROTORS: Catalogue<Rotor> = &[
+    ("I", Rotor::new_g260_i), ("II", Rotor::new_g260_ii), ("III", Rotor::new_g260_iii),
+];
+
+const G31_ROTORS: Catalogue<Rotor> = &[
+    ("I", Rotor::new_g31_i), ("II", Rotor::new_g31_ii), ("III", Rotor::new_g31_iii),
+];
+
+const RAILWAY_ROTORS: Catalogue<Rotor> = &[
+    ("I", Rotor::new_railway_i), ("II", Rotor::new_railway_ii), ("III", Rotor::new_railway_iii),
+];
+
+const T_ROTORS: Catalogue<Rotor> = &[
+    ("I", Rotor::new_t_i), ("II", Rotor::new_t_ii), ("III", Rotor::new_t_iii),
+    ("IV", Rotor::new_t_iv), ("V", Rotor::new_t_v), ("VI", Rotor::new_t_vi),
+    ("VII", Rotor::new_t_vii), ("VIII", Rotor::new_t_viii),
+];
+
+const MILITARY_REFLECTORS: Catalogue<Reflector> = &[
+    ("A", Reflector::new_a), ("B", Reflector::new_b), ("C", Reflector::new_c),
+];
+
+const THIN_REFLECTORS: Catalogue<Reflector> = &[
+    ("B-thin", Reflector::new_b_thin), ("C-thin", Reflector::new_c_thin),
+];
+
+const COMMERCIAL_REFLECTORS: Catalogue<Reflector> = &[("UKW", Reflector::new_k)];
+
+const G312_REFLECTORS: Catalogue<Reflector> = &[("UKW", Reflector::new_g312)];
+
+const RAILWAY_REFLECTORS: Catalogue<Reflector> = &[("UKW", Reflector::new_railway)];
+
+const T_REFLECTORS: Catalogue<Reflector> = &[("UKW", Reflector::new_t)];
+
+/// Name of the rewirable reflector (UKW-D), which is plugged rather than chosen from a catalogue
+pub(crate) const REWIRABLE_REFLECTOR: &str = "D";
+
+/// How the rotors of a model are driven when a key is pressed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StepMechanism {
+    /// Pawls pushing on the notch of the rotor to their right, which gives the double step
+    Pawl,
+    /// Gear driven stepping of the Enigma G, where a rotor moves its left neighbor as it
+    /// passes one of its notches like an odometer, and the leftmost rotor drives the reflector
+    Cog,
+}
+
+/// The Enigma models which can be simulated, each with its own catalogue of rotors and
+/// reflectors, entry wheel, and rotor layout
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Model {
+    /// Wehrmacht and Luftwaffe Enigma I, along with the naval M3 (rotors I-VIII)
+    EnigmaI,
+    /// Naval four rotor M4, with a Greek wheel and thin reflectors
+    M4,
+    /// Commercial Enigma D, with a settable reflector and no plugboard
+    D,
+    /// Commercial Enigma K, wired the same way as the Enigma D
+    K,
+    /// Enigma K used by the Swiss army, with rewired rotors
+    SwissK,
+    /// Abwehr Enigma G-312, with cog driven stepping and a stepping reflector
+    G312,
+    /// Enigma G-260, with cog driven stepping and a stepping reflector
+    G260,
+    /// Enigma G-31, with cog driven stepping and a stepping reflector
+    G31,
+    /// Railway Enigma (Rocket) of the Reichsbahn, with a settable reflector and no plugboard
+    Railway,
+    /// Enigma T (Tirpitz) built for Japan, with five notches per rotor and its own entry wheel
+    T,
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Model {
+    type Err = EnigmaError;
+
+    /// Parse a model from its name, ignoring case ("M3" is accepted for the Enigma I)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "enigmai" | "enigma-i" | "i" | "m3" => Ok(Model::EnigmaI),
+            "m4" => Ok(Model::M4),
+            "d" => Ok(Model::D),
+            "k" => Ok(Model::K),
+            "swissk" | "swiss-k" => Ok(Model::SwissK),
+            "g312" | "g-312" => Ok(Model::G312),
+            "g260" | "g-260" => Ok(Model::G260),
+            "g31" | "g-31" => Ok(Model::G31),
+            "railway" | "rocket" => Ok(Model::Railway),
+            "t" | "tirpitz" => Ok(Model::T),
+            _ => Err(EnigmaError::InvalidModel(s.to_string())),
+        }
+    }
+}
+
+impl Model {
+    /// Every available model
+    pub const ALL: [Model; 10] = [Model::EnigmaI, Model::M4, Model::D, Model::K, Model::SwissK,
+        Model::G312, Model::G260, Model::G31, Model::Railway, Model::T];
+
+    /// Short name of the model, which can be parsed back into the model
+    pub fn name(&self) -> &'static str {
+        match self {
+            Model::EnigmaI => "EnigmaI",
+            Model::M4 => "M4",
+            Model::D => "D",
+            Model::K => "K",
+            Model::SwissK => "SwissK",
+            Model::G312 => "G312",
+            Model::G260 => "G260",
+            Model::G31 => "G31",
+            Model::Railway => "Railway",
+            Model::T => "T",
+        }
+    }
+
+    /// Layout of the rotor slots of the model, from left to right
+    pub fn slots(&self) -> Vec<SlotKind> {
+        match self {
+            Model::M4 => vec![SlotKind::Greek, SlotKind::Stepping, SlotKind::Stepping,
+                              SlotKind::Stepping],
+            _ => vec![SlotKind::Stepping; 3],
+        }
+    }
+
+    /// How the rotors of the model are driven
+    pub fn step_mechanism(&self) -> StepMechanism {
+        match self {
+            Model::G312 | Model::G260 | Model::G31 => StepMechanism::Cog,
+            _ => StepMechanism::Pawl,
+        }
+    }
+
+    /// Whether the reflector is stepped along with the rotors
+    pub fn reflector_steps(&self) -> bool {
+        self.step_mechanism() == StepMechanism::Cog
+    }
+
+    /// Whether the rewirable reflector (UKW-D) can be used in the model
+    pub fn has_rewirable_reflector(&self) -> bool {
+        matches!(self, Model::EnigmaI)
+    }
+
+    /// Whether the model has a plugboard
+    pub fn has_plugboard(&self) -> bool {
+        matches!(self, Model::EnigmaI | Model::M4)
+    }
+
+    /// Names of the rotors which can be placed in the stepping slots of the model
+    pub fn rotor_names(&self) -> Vec<&'static str> {
+        self.rotors().iter().map(|(name, _)| *name).collect()
+    }
+
+    /// Names of the Greek wheels of the model (empty if the model has none)
+    pub fn greek_wheel_names(&self) -> Vec<&'static str> {
+        self.greek_wheels().iter().map(|(name, _)| *name).collect()
+    }
+
+    /// Names of the reflectors which can be used in the model
+    pub fn reflector_names(&self) -> Vec<&'static str> {
+        self.reflectors().iter().map(|(name, _)| *name).collect()
+    }
+
+    /// Look up a rotor of the model by name, roman numeral names are case sensitive
+    pub(crate) fn rotor(&self, name: &str) -> Option<Rotor> {
+        Self::lookup(self.rotors(), name, false)
+    }
+
+    /// Look up a Greek wheel of the model by name, ignoring case
+    pub(crate) fn greek_wheel(&self, name: &str) -> Option<Rotor> {
+        Self::lookup(self.greek_wheels(), name, true)
+    }
+
+    /// Look up a reflector of the model by name, ignoring case
+    pub(crate) fn reflector(&self, name: &str) -> Option<Reflector> {
+        Self::lookup(self.reflectors(), name, true)
+    }
+
+    /// Entry wheel of the model
+    pub(crate) fn entry_wheel(&self) -> EntryWheel {
+        match self {
+            Model::EnigmaI | Model::M4 => EntryWheel::new_military(),
+            Model::T => EntryWheel::new_t(),
+            _ => EntryWheel::new_qwertzu(),
+        }
+    }
+
+    fn rotors(&self) -> Catalogue<Rotor> {
+        match self {
+            Model::EnigmaI | Model::M4 => MILITARY_ROTORS,
+            Model::D | Model::K => COMMERCIAL_ROTORS,
+            Model::SwissK => SWISS_K_ROTORS,
+            Model::G312 => G312_ROTORS,
+            Model::G260 => G260_ROTORS,
+            Model::G31 => G31_ROTORS,
+            Model::Railway => RAILWAY_ROTORS,
+            Model::T => T_ROTORS,
+        }
+    }
+
+    fn greek_wheels(&self) -> Catalogue<Rotor> {
+        match self {
+            Model::M4 => GREEK_WHEELS,
+            _ => &[],
+        }
+    }
+
+    fn reflectors(&self) -> Catalogue<Reflector> {
+        match self {
+            Model::EnigmaI => MILITARY_REFLECTORS,
+            Model::M4 => THIN_REFLECTORS,
+            Model::D | Model::K | Model::SwissK | Model::G260 | Model::G31 => COMMERCIAL_REFLECTORS,
+            Model::G312 => G312_REFLECTORS,
+            Model::Railway => RAILWAY_REFLECTORS,
+            Model::T => T_REFLECTORS,
+        }
+    }
+
+    /// Find a named entry in a catalogue
+    fn lookup<T>(catalogue: Catalogue<T>, name: &str, ignore_case: bool) -> Option<T> {
+        catalogue.iter()
+            .find(|(entry, _)| *entry == name || (ignore_case && entry.eq_ignore_ascii_case(name)))
+            .map(|(_, create)| create())
+    }
+}
+
+#[cfg(test)]
+mod test_model {
+    use super::*;
+
+    #[test]
+    fn test_catalogues() {
+        assert!(Model::EnigmaI.rotor("VIII").is_some());
+        assert!(Model::EnigmaI.rotor("Beta").is_none());
+        assert!(Model::M4.greek_wheel("gamma").is_some());
+        assert!(Model::M4.reflector("b-thin").is_some());
+        assert!(Model::M4.reflector("B").is_none());
+        assert!(Model::EnigmaI.reflector("b").is_some());
+        assert!(Model::K.rotor("IV").is_none());
+        assert!(Model::K.reflector("UKW").is_some());
+        assert!(!Model::SwissK.has_plugboard());
+    }
+
+    #[test]
+    fn test_names() {
+        for model in Model::ALL {
+            assert_eq!(model, model.name().parse::<Model>().ok().unwrap());
+        }
+        assert_eq!(Model::EnigmaI, "M3".parse::<Model>().ok().unwrap());
+        assert!("Z".parse::<Model>().is_err());
+    }
+}
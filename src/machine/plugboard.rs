@@ -1,15 +1,144 @@
+use crate::machine::enigma::EnigmaError;
 use std::fmt;
 use std::collections::HashSet;
+use std::str::FromStr;
+
+/// Wiring of the rotating disc of the Enigma-Uhr, contact i on the side of the a plugs
+/// is connected to contact UHR_WIRING[i] on the side of the b plugs
+const UHR_WIRING: [u8; 40] = [6, 31, 4, 29, 18, 39, 16, 25, 30, 23, 28, 1, 38, 11, 36, 37, 26,
+    27, 24, 21, 14, 3, 12, 17, 2, 7, 0, 33, 10, 35, 8, 5, 22, 19, 20, 13, 34, 15, 32, 9];
+
+/// Position around the Uhr of the b plug with each number. Plug ia has its thick pin on
+/// contact 4i and its thin pin on contact 4i+2, and the same holds for the b plug at
+/// position k, so at setting 00 plug ia is connected to plug ib like an ordinary cable.
+const UHR_B_PLUGS: [u8; 10] = [1, 4, 7, 9, 6, 3, 0, 2, 5, 8];
 
 /// Struct representing the plugboard.
 /// This translates characters through
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct Plugboard {
     wires: [u8; 26],
+    /// Uhr switch box replacing the plugboard cables, if it is attached
+    uhr: Option<Uhr>,
+}
+
+/// Enigma-Uhr attachment, which connects ten a/b plug pairs through a 40 position rotary
+/// switch. Signals entering through a thick pin leave through a thin pin, so the letter
+/// swaps on the way into the machine differ from the ones on the way out.
+#[derive(Clone, PartialEq, Debug)]
+struct Uhr {
+    /// Letters plugged into the sockets by plugs 1a-10a and 1b-10b
+    pairs: [(u8, u8); 10],
+    /// Uhr setting 00-39
+    setting: u8,
+    /// Letter swaps on the way into the machine (keyboard to rotors)
+    path_in: [u8; 26],
+    /// Letter swaps on the way out of the machine (rotors to lamps)
+    path_out: [u8; 26],
+}
+
+impl Uhr {
+    fn new(pairs: [(u8, u8); 10], setting: u8) -> Self {
+        let mut uhr = Self {
+            pairs,
+            setting,
+            path_in: [0; 26],
+            path_out: [0; 26],
+        };
+        uhr.set(setting);
+        uhr
+    }
+
+    /// Turn the Uhr to a new setting, recalculating the letter swaps
+    fn set(&mut self, setting: u8) {
+        let mut wiring_rev: [u8; 40] = [0; 40];
+        for (i, contact) in UHR_WIRING.iter().enumerate() {
+            wiring_rev[*contact as usize] = i as u8;
+        }
+        let mut b_labels: [usize; 10] = [0; 10];
+        for (label, position) in UHR_B_PLUGS.iter().enumerate() {
+            b_labels[*position as usize] = label;
+        }
+        // Follow a signal from the thick pin at a position, through the disc, to the
+        // position of the thin pin it comes out of
+        let through = |wiring: &[u8; 40], position: usize| -> usize {
+            let contact = (4 * position + setting as usize) % 40;
+            let out = (wiring[contact] as usize + 40 - setting as usize) % 40;
+            (out - 2) / 4
+        };
+        let mut path_in: [u8; 26] = [0; 26];
+        for (i, wire) in path_in.iter_mut().enumerate() {
+            *wire = i as u8;
+        }
+        for (label, (a, b)) in self.pairs.iter().enumerate() {
+            let to_b = b_labels[through(&UHR_WIRING, label)];
+            path_in[*a as usize] = self.pairs[to_b].1;
+            let to_a = through(&wiring_rev, UHR_B_PLUGS[label] as usize);
+            path_in[*b as usize] = self.pairs[to_a].0;
+        }
+        let mut path_out: [u8; 26] = [0; 26];
+        for (i, wire) in path_in.iter().enumerate() {
+            path_out[*wire as usize] = i as u8;
+        }
+        self.setting = setting;
+        self.path_in = path_in;
+        self.path_out = path_out;
+    }
 }
 
 impl fmt::Display for Plugboard {
     fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result{
+        match self.uhr() {
+            Some((pairs, setting)) => write!(f, "Uhr {:02}: {}", setting, pairs),
+            None => write!(f, "{}", self.wires()),
+        }
+    }
+}
+
+impl FromStr for Plugboard {
+    type Err = EnigmaError;
+
+    /// Parse a plugboard from its `Display` form, either the wires "a-b,c-d,..." (empty for
+    /// no wires) or "Uhr <setting>: a-b,c-d,..." with the ten ordered pairs of the Uhr
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut plugboard = Plugboard::new();
+        if let Some(uhr) = s.strip_prefix("Uhr ") {
+            let invalid = |reason: &str| EnigmaError::InvalidUhr(format!("{} ({})", s, reason));
+            let (setting, pairs) = uhr.split_once(": ")
+                .ok_or_else(|| invalid("expected Uhr <setting>: <pairs>"))?;
+            let setting: u8 = setting.parse().map_err(|_| invalid("setting must be 00-39"))?;
+            let pairs = pairs.split(',').map(Self::parse_wire).collect::<Result<Vec<_>, _>>()?;
+            plugboard.attach_uhr(&pairs, setting).map_err(|err| invalid(&err.to_string()))?;
+            return Ok(plugboard);
+        }
+        if s.is_empty() {
+            return Ok(plugboard);
+        }
+        for wire in s.split(',') {
+            let (start, end) = Self::parse_wire(wire)?;
+            if plugboard.add_wire(start, end).is_err() {
+                return Err(EnigmaError::PlugboardWireOverlap(format!("{} in {}", wire, s)));
+            }
+        }
+        Ok(plugboard)
+    }
+}
+
+impl Plugboard {
+    /// Create a new plugboard instance, with no wires added
+    pub fn new() -> Plugboard {
+        let mut wires: [u8; 26] = [0; 26];
+        for (i, wire) in wires.iter_mut().enumerate() {
+            *wire = i as u8;
+        }
+        Plugboard {
+            wires,
+            uhr: None,
+        }
+    }
+
+    /// Wires in the plugboard, in the form "a-b,c-d,..." (empty while the Uhr is attached)
+    pub fn wires(&self) -> String {
         let mut display = String::new();
         let mut completed: HashSet<char> = HashSet::new();
         for (idx, val) in self.wires.iter().enumerate() {
@@ -21,7 +150,7 @@ impl fmt::Display for Plugboard {
                 }
                 // If the wire has not yet been added to the representation
                 if !completed.contains(&start) && !completed.contains(&end){
-                    if display.len() == 0{
+                    if display.is_empty(){
                         display.push_str(&format!("{}-{}", start, end))
                     } else {
                         display.push_str(&format!(",{}-{}", start, end))
@@ -32,25 +161,69 @@ impl fmt::Display for Plugboard {
                 }
             }
         }
-        write!(f, "{}", display)
+        display
     }
-}
 
-impl Plugboard {
-    /// Create a new plugboard instance, with no wires added
-    pub fn new() -> Plugboard {
-        let mut wires: [u8; 26] = [0; 26];
-        for i in 0..26 {
-            wires[i] = i as u8;
+    /// Ordered pairs plugged into the attached Uhr in the form "a-b,c-d,...", along with
+    /// the Uhr setting, or None if there is no Uhr
+    pub fn uhr(&self) -> Option<(String, u8)> {
+        self.uhr.as_ref().map(|uhr| {
+            let pairs: Vec<String> = uhr.pairs.iter()
+                .map(|(a, b)| format!("{}-{}", (a + 97u8) as char, (b + 97u8) as char))
+                .collect();
+            (pairs.join(","), uhr.setting)
+        })
+    }
+
+    /// Attach the Uhr in place of the plugboard cables. Takes ten ordered pairs of letters,
+    /// where the first letter of pair i is plugged with plug ia and the second with plug ib,
+    /// along with the Uhr setting (0..40).
+    pub fn attach_uhr(&mut self, pairs: &[(char, char)], setting: u8) -> Result<(), PlugboardError> {
+        if setting >= 40 {
+            return Err(PlugboardError::InvalidUhrSetting);
         }
-        Plugboard {
-            wires
+        let pairs: [(char, char); 10] = match pairs.try_into() {
+            Ok(pairs) => pairs,
+            Err(_) => { return Err(PlugboardError::WrongPairCount); }
+        };
+        let mut used: HashSet<u8> = HashSet::new();
+        let pairs = pairs.map(|(a, b)| (a.to_ascii_lowercase() as u8 - 97u8,
+                                        b.to_ascii_lowercase() as u8 - 97u8));
+        for (a, b) in pairs.iter() {
+            if !used.insert(*a) || !used.insert(*b) {
+                return Err(PlugboardError::OverlappingWires);
+            }
         }
+        *self = Plugboard::new();
+        self.uhr = Some(Uhr::new(pairs, setting));
+        Ok(())
+    }
+
+    /// Turn the attached Uhr to a new setting (0..40)
+    pub fn set_uhr(&mut self, setting: u8) -> Result<(), PlugboardError> {
+        if setting >= 40 {
+            return Err(PlugboardError::InvalidUhrSetting);
+        }
+        match self.uhr.as_mut() {
+            Some(uhr) => {
+                uhr.set(setting);
+                Ok(())
+            }
+            None => Err(PlugboardError::NoUhr),
+        }
+    }
+
+    /// Remove the Uhr, leaving a plugboard with no wires
+    pub fn detach_uhr(&mut self) {
+        self.uhr = None;
     }
 
     /// Add a wire between the start and end char. The chars
     /// can be upper or lowercase.
     pub fn add_wire(&mut self, start: char, end: char) -> Result<(),PlugboardError> {
+        if self.uhr.is_some() {
+            return Err(PlugboardError::UhrAttached);
+        }
         let start = start.to_ascii_lowercase() as u8 - 97u8;
         let end = end.to_ascii_lowercase() as u8 - 97u8;
         // Check if there is already a wire originating from one of the ends
@@ -70,6 +243,9 @@ impl Plugboard {
     /// Remove a wire from the plugboard which starts at start, and ends at end
     /// (direction unimportant, so a-e and e-a are equivalent)
     pub fn remove_wire(&mut self, start: char, end:char)->Result<(),PlugboardError>{
+        if self.uhr.is_some() {
+            return Err(PlugboardError::UhrAttached);
+        }
         let start = start.to_ascii_lowercase() as u8 - 97u8;
         let end = end.to_ascii_lowercase() as u8 - 97u8;
         if self.wires[start as usize] == end && self.wires[end as usize]==start{
@@ -80,21 +256,61 @@ impl Plugboard {
         Err(PlugboardError::WireDoesntExist)
     }
 
-    /// Take in a char and translate it through the plugboard
+    /// Take in a char and translate it through the plugboard on the way into the machine
     pub fn translate_char(&self, input_char: char) -> char {
         let input_val = input_char.to_ascii_lowercase() as u8 - 97u8;
         (self.translate_u8(input_val) + 97u8) as char
     }
 
+    /// Take in a char and translate it through the plugboard on the way out of the machine,
+    /// which only differs from the way in when the Uhr is attached
+    pub fn translate_char_reverse(&self, input_char: char) -> char {
+        let input_val = input_char.to_ascii_lowercase() as u8 - 97u8;
+        match &self.uhr {
+            Some(uhr) => (uhr.path_out[input_val as usize] + 97u8) as char,
+            None => (self.translate_u8(input_val) + 97u8) as char,
+        }
+    }
+
+    /// Parse a wire of the form "<start>-<end>" between two different letters
+    fn parse_wire(wire: &str) -> Result<(char, char), EnigmaError> {
+        let letters: Vec<char> = wire.chars().collect();
+        match letters[..] {
+            [start, '-', end] if start.is_ascii_alphabetic() && end.is_ascii_alphabetic()
+                && !start.eq_ignore_ascii_case(&end) => Ok((start, end)),
+            _ => Err(EnigmaError::InvalidPlugboardWire(wire.to_string())),
+        }
+    }
+
     /// Take in u8 representing a char and translate it through the plugboard
     fn translate_u8(&self, input_val: u8) -> u8 {
-        self.wires[input_val as usize]
+        match &self.uhr {
+            Some(uhr) => uhr.path_in[input_val as usize],
+            None => self.wires[input_val as usize],
+        }
     }
 }
 
 pub enum PlugboardError{
     WireDoesntExist,
     OverlappingWires,
+    WrongPairCount,
+    InvalidUhrSetting,
+    NoUhr,
+    UhrAttached,
+}
+
+impl fmt::Display for PlugboardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlugboardError::WireDoesntExist => { write!(f, "wire doesn't exist") }
+            PlugboardError::OverlappingWires => { write!(f, "overlapping pairs") }
+            PlugboardError::WrongPairCount => { write!(f, "expected 10 pairs") }
+            PlugboardError::InvalidUhrSetting => { write!(f, "setting must be 00-39") }
+            PlugboardError::NoUhr => { write!(f, "no Uhr attached") }
+            PlugboardError::UhrAttached => { write!(f, "Uhr is attached") }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -110,4 +326,52 @@ mod test_plugboard {
         assert_eq!('b', test_board.translate_char('z'));
         assert_eq!('z', test_board.translate_char('b'));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_uhr(){
+        let pairs: Vec<(char, char)> = vec![('a', 'b'), ('c', 'd'), ('e', 'f'), ('g', 'h'),
+            ('i', 'j'), ('k', 'l'), ('m', 'n'), ('o', 'p'), ('q', 'r'), ('s', 't')];
+        let mut test_board = Plugboard::new();
+        assert!(test_board.attach_uhr(&pairs, 0).is_ok());
+        // At setting 00 the Uhr behaves like ordinary cables
+        for (a, b) in pairs.iter() {
+            assert_eq!(*b, test_board.translate_char(*a));
+            assert_eq!(*a, test_board.translate_char(*b));
+            assert_eq!(*b, test_board.translate_char_reverse(*a));
+        }
+        assert_eq!('z', test_board.translate_char('z'));
+        // Settings which are not a multiple of 4 give non reciprocal swaps
+        assert!(test_board.set_uhr(27).is_ok());
+        assert!(('a'..='z').any(|c| test_board.translate_char(test_board.translate_char(c)) != c));
+        for c in 'a'..='z' {
+            assert_eq!(c, test_board.translate_char_reverse(test_board.translate_char(c)));
+        }
+        // Reference letters at setting 27, worked out separately by following the published
+        // disc wiring from the a plug contacts (0, 4, ..., 36) and b plug contacts (1b to 10b
+        // on 4, 16, 28, 36, 24, 12, 0, 8, 20, 32)
+        let swapped: String = ('a'..='t').map(|c| test_board.translate_char(c)).collect();
+        assert_eq!("bgdqjerknmhilopatsfc", swapped);
+        assert!(test_board.set_uhr(40).is_err());
+        assert!(test_board.add_wire('x', 'y').is_err());
+        assert!(test_board.attach_uhr(&pairs[1..], 0).is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut board = Plugboard::new();
+        let _ = board.add_wire('q', 'c');
+        let _ = board.add_wire('x', 'a');
+        assert_eq!("a-x,c-q", board.to_string());
+        assert_eq!(board, board.to_string().parse::<Plugboard>().ok().unwrap());
+        assert_eq!(Plugboard::new(), "".parse::<Plugboard>().ok().unwrap());
+        let pairs = [('a', 'b'), ('c', 'd'), ('e', 'f'), ('g', 'h'), ('i', 'j'),
+            ('k', 'l'), ('m', 'n'), ('o', 'p'), ('q', 'r'), ('s', 't')];
+        let _ = board.attach_uhr(&pairs, 13);
+        assert_eq!(board, board.to_string().parse::<Plugboard>().ok().unwrap());
+        let error = |s: &str| s.parse::<Plugboard>().err().unwrap().to_string();
+        assert_eq!("Invalid Plugboard Wire: a-", error("a-"));
+        assert_eq!("Plugboard Wire Overlap: a-c in a-b,a-c", error("a-b,a-c"));
+        assert_eq!("Invalid Uhr: Uhr 40: a-b (setting must be 00-39)", error("Uhr 40: a-b"));
+        assert_eq!("Invalid Uhr: Uhr 01: a-b (expected 10 pairs)", error("Uhr 01: a-b"));
+    }
+}
@@ -1,8 +1,10 @@
+use crate::machine::enigma::EnigmaError;
 use std::fmt;
+use std::str::FromStr;
 
 /// Represents the reflector, which turns the signal around on the right side
 /// of the machine
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct Reflector {
     configuration: [u8;26],
     offset: u8,
@@ -13,7 +15,38 @@ impl fmt::Display for Reflector {
         let wire_configuration:String = self.configuration.iter()
             .map(|v| (v+97u8) as char)
             .collect();
-        write!(f, "Wire Configuration: {}, Offset: {}", wire_configuration, (self.offset+97u8) as char)
+        write!(f, "Wire Configuration: {}, Offset: {}", wire_configuration, self.position())
+    }
+}
+
+impl FromStr for Reflector {
+    type Err = EnigmaError;
+
+    /// Parse a reflector from its `Display` form, for example "Wire Configuration:
+    /// yruhqsldpxngokmiebfzcwvjat, Offset: a". The wiring must swap pairs of letters, with
+    /// no letter wired to itself.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| EnigmaError::InvalidReflectorWiring(format!("{} ({})", s, reason));
+        let rest = s.strip_prefix("Wire Configuration: ")
+            .ok_or_else(|| invalid("expected Wire Configuration"))?;
+        let (wiring, offset) = rest.split_once(", Offset: ")
+            .ok_or_else(|| invalid("expected Offset"))?;
+        let configuration: Vec<u8> = wiring.to_ascii_lowercase().bytes()
+            .map(|b| b.wrapping_sub(97u8))
+            .collect();
+        if configuration.len() != 26 || configuration.iter().any(|v| *v >= 26) {
+            return Err(invalid("wiring must be 26 letters"));
+        }
+        for (i, v) in configuration.iter().enumerate() {
+            if *v as usize == i || configuration[*v as usize] as usize != i {
+                return Err(invalid("wiring must swap pairs of different letters"));
+            }
+        }
+        let mut letters = offset.chars();
+        match (letters.next(), letters.next()) {
+            (Some(c), None) if c.is_ascii_alphabetic() => Ok(Self::new(wiring, c)),
+            _ => Err(invalid("offset must be a letter")),
+        }
     }
 }
 
@@ -32,6 +65,53 @@ impl Reflector{
         }
     }
 
+    /// Create a rewirable reflector (UKW-D) from 12 plugged pairs of letters. The letters are
+    /// in Bletchley Park notation, where J and Y are wired together inside the reflector and
+    /// can't be plugged (the pair J-Y may be given, but has no effect).
+    pub fn new_ukw_d(pairs: &[(char, char)]) -> Result<Self, ReflectorError> {
+        const FIXED: (u8, u8) = (b'j' - 97u8, b'y' - 97u8);
+        let mut configuration: [u8; 26] = [u8::MAX; 26];
+        configuration[FIXED.0 as usize] = FIXED.1;
+        configuration[FIXED.1 as usize] = FIXED.0;
+        for (start, end) in pairs {
+            if !start.is_ascii_alphabetic() || !end.is_ascii_alphabetic() {
+                return Err(ReflectorError::InvalidLetter);
+            }
+            let start = start.to_ascii_lowercase() as u8 - 97u8;
+            let end = end.to_ascii_lowercase() as u8 - 97u8;
+            if (start, end) == FIXED || (end, start) == FIXED {
+                continue;
+            }
+            if start == FIXED.0 || start == FIXED.1 || end == FIXED.0 || end == FIXED.1 {
+                return Err(ReflectorError::FixedPair);
+            }
+            // A letter can't be plugged to itself or to two other letters
+            if start == end || configuration[start as usize] != u8::MAX
+                || configuration[end as usize] != u8::MAX {
+                return Err(ReflectorError::OverlappingPairs);
+            }
+            configuration[start as usize] = end;
+            configuration[end as usize] = start;
+        }
+        // Every letter must be plugged, so the wiring has no fixed points
+        if configuration.contains(&u8::MAX) {
+            return Err(ReflectorError::IncompletePairs);
+        }
+        Ok(Self {
+            configuration,
+            offset: 0,
+        })
+    }
+
+    /// Pairs of letters wired together by the reflector, in the form "a-b,c-d,..."
+    pub fn pairs(&self) -> String {
+        self.configuration.iter().enumerate()
+            .filter(|(start, end)| (*start as u8) < **end)
+            .map(|(start, end)| format!("{}-{}", (start as u8 + 97u8) as char, (end + 97u8) as char))
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+
     /// Translate a character through the reflector
     pub fn translate(&self, input: char)->char{
         let input_val: u8 = input as u8 -97u8;
@@ -44,6 +124,17 @@ impl Reflector{
         self.offset = setting.to_ascii_lowercase() as u8 - 97u8;
     }
 
+    /// Current position of the reflector
+    pub fn position(&self) -> char {
+        (self.offset+97u8) as char
+    }
+
+    /// Step the reflector forward by one position, on machines where it is driven
+    /// by the rotors
+    pub fn step(&mut self){
+        self.offset = (self.offset+1)%26;
+    }
+
     pub fn new_a() ->Self {
         Self::new("EJMZALYXVBWFCRQUONTSPIKHGD", 'a')
     }
@@ -56,6 +147,36 @@ impl Reflector{
         Self::new("FVPJIAOYEDRZXWGCTKUQSBNMHL", 'a')
     }
 
+    /// Thin reflector B (B-Dünn) of the M4, used together with a Greek wheel
+    pub fn new_b_thin() ->Self {
+        Self::new("ENKQAUYWJICOPBLMDXZVFTHRGS", 'a')
+    }
+
+    /// Thin reflector C (C-Dünn) of the M4, used together with a Greek wheel
+    pub fn new_c_thin() ->Self {
+        Self::new("RDOBJNTKVEHMLFCWZAXGYIPSUQ", 'a')
+    }
+
+    /// Settable reflector of the commercial Enigma D and Enigma K (also used on the Swiss K)
+    pub fn new_k() ->Self {
+        Self::new("IMETCGFRAYSQBZXWLHKDVUPOJN", 'a')
+    }
+
+    /// Settable and stepping reflector of the Abwehr Enigma G-312
+    pub fn new_g312() ->Self {
+        Self::new("RULQMZJSYGOCETKWDAHNBXPVIF", 'a')
+    }
+
+    /// Settable reflector of the Railway Enigma
+    pub fn new_railway() ->Self {
+        Self::new("QYHOGNECVPUZTFDJAXWMKISRBL", 'a')
+    }
+
+    /// Settable reflector of the Enigma T
+    pub fn new_t() ->Self {
+        Self::new("GEKPBTAUMOCNILJDXZYFHWVQSR", 'a')
+    }
+
     fn wrap_26_sub(lhs:u8, rhs:u8)->u8{
         if rhs <= lhs {
             lhs - rhs
@@ -65,6 +186,13 @@ impl Reflector{
     }
 }
 
+pub enum ReflectorError{
+    InvalidLetter,
+    OverlappingPairs,
+    FixedPair,
+    IncompletePairs,
+}
+
 #[cfg(test)]
 mod test_reflector{
     use super::*;
@@ -83,6 +211,39 @@ mod test_reflector{
         test_loop(&test_reflector);
         let test_reflector  = Reflector::new_c();
         test_loop(&test_reflector);
+        let test_reflector  = Reflector::new_b_thin();
+        test_loop(&test_reflector);
+        let test_reflector  = Reflector::new_c_thin();
+        test_loop(&test_reflector);
+        let test_reflector  = Reflector::new_k();
+        test_loop(&test_reflector);
+        let test_reflector  = Reflector::new_g312();
+        test_loop(&test_reflector);
+        let test_reflector  = Reflector::new_railway();
+        test_loop(&test_reflector);
+        let test_reflector  = Reflector::new_t();
+        test_loop(&test_reflector);
+    }
+
+    #[test]
+    fn test_ukw_d(){
+        let pairs: Vec<(char, char)> = vec![('a', 'c'), ('b', 'o'), ('d', 'i'), ('e', 'g'),
+            ('f', 'h'), ('k', 'q'), ('l', 'x'), ('m', 'r'), ('n', 's'), ('p', 'w'), ('t', 'v'),
+            ('u', 'z')];
+        let test_reflector = Reflector::new_ukw_d(&pairs).ok().unwrap();
+        test_loop(&test_reflector);
+        for c in 'a'..='z'{
+            assert_ne!(c, test_reflector.translate(c));
+        }
+        assert_eq!('y', test_reflector.translate('j'));
+        assert_eq!("a-c,b-o,d-i,e-g,f-h,j-y,k-q,l-x,m-r,n-s,p-w,t-v,u-z", test_reflector.pairs());
+        // Too few pairs, overlapping pairs, and pairs using J or Y are rejected
+        assert!(Reflector::new_ukw_d(&pairs[1..]).is_err());
+        let mut bad_pairs = pairs.clone();
+        bad_pairs[0] = ('a', 'b');
+        assert!(Reflector::new_ukw_d(&bad_pairs).is_err());
+        bad_pairs[0] = ('a', 'j');
+        assert!(Reflector::new_ukw_d(&bad_pairs).is_err());
     }
 
     fn test_loop(reflector: &Reflector){
@@ -90,4 +251,16 @@ mod test_reflector{
             assert_eq!(c, reflector.translate(reflector.translate(c)))
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse() {
+        let mut reflector = Reflector::new_c_thin();
+        reflector.set('k');
+        assert_eq!(reflector, reflector.to_string().parse::<Reflector>().ok().unwrap());
+        let error = |s: &str| s.parse::<Reflector>().err().unwrap().to_string();
+        assert_eq!("Invalid Reflector Wiring: Wire Configuration: abcdefghijklmnopqrstuvwxyz, \
+        Offset: a (wiring must swap pairs of different letters)",
+                   error("Wire Configuration: abcdefghijklmnopqrstuvwxyz, Offset: a"));
+        assert!(error("Wire Configuration: yruhqsldpxngokmiebfzcwvjat").ends_with("(expected Offset)"));
+    }
+}
@@ -1,10 +1,12 @@
+use crate::machine::enigma::EnigmaError;
 use std::collections::HashSet;
 use std::fmt;
+use std::str::FromStr;
 
 /// Represents the rotors of the Enigma machine. Translates characters passed into the rotor,
 /// steps the rotor when required (either each letter in the case of the rightmost rotor,
-/// or when the rotor to the right hits its notch).
-#[derive(Clone)]
+/// or when a pawl engages with its notch or the notch of the rotor to its right).
+#[derive(Clone, PartialEq, Debug)]
 pub(crate) struct Rotor {
     /// Which letters/positions are translated by this rotor
     /// when going forward
@@ -14,6 +16,9 @@ pub(crate) struct Rotor {
     path_rev: [u8;26],
     /// Current offset of the rotor from 0 (always 0..26)
     offset: u8,
+    /// Ring setting (Ringstellung) of the rotor from 0 (always 0..26), shifts the
+    /// wiring relative to the letters on the rotor
+    ring: u8,
     /// Which steps will cause the left neighbor rotor to step as well
     notches: HashSet<u8>,
 }
@@ -23,13 +28,46 @@ impl fmt::Display for Rotor {
         let wire_configuration: String = self.path_fwd.iter()
             .map(|v| (v+97u8) as char)
             .collect();
-        let current_setting = (self.offset+97u8) as char;
+        let current_setting = self.position();
+        let ring_setting = self.ring();
         let mut notches_vec:Vec<char> = self.notches.iter().map(|v| (v+97u8) as char)
             .collect::<Vec<char>>();
         notches_vec.sort();
         let notches_str = notches_vec.iter().collect::<String>();
-        write!(f, "Wire Configuration: {}, Notches: {}, Current Setting: {}", wire_configuration,
-        notches_str, current_setting)
+        write!(f, "Wire Configuration: {}, Notches: {}, Ring Setting: {}, Current Setting: {}",
+               wire_configuration, notches_str, ring_setting, current_setting)
+    }
+}
+
+impl FromStr for Rotor {
+    type Err = EnigmaError;
+
+    /// Parse a rotor from its `Display` form, for example "Wire Configuration:
+    /// ekmflgdqvzntowyhxuspaibrcj, Notches: q, Ring Setting: a, Current Setting: a"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| EnigmaError::InvalidRotor(format!("{} ({})", s, reason));
+        let rest = s.strip_prefix("Wire Configuration: ")
+            .ok_or_else(|| invalid("expected Wire Configuration"))?;
+        let (wiring, rest) = rest.split_once(", Notches: ")
+            .ok_or_else(|| invalid("expected Notches"))?;
+        let (notches, rest) = rest.split_once(", Ring Setting: ")
+            .ok_or_else(|| invalid("expected Ring Setting"))?;
+        let (ring, setting) = rest.split_once(", Current Setting: ")
+            .ok_or_else(|| invalid("expected Current Setting"))?;
+        let mut letters: Vec<char> = wiring.to_ascii_lowercase().chars().collect();
+        letters.sort();
+        if !letters.into_iter().eq('a'..='z') {
+            return Err(invalid("wiring must contain each letter once"));
+        }
+        if !notches.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(invalid("notches must be letters"));
+        }
+        let ring = Self::single_letter(ring).ok_or_else(|| invalid("ring setting must be a letter"))?;
+        let setting = Self::single_letter(setting)
+            .ok_or_else(|| invalid("current setting must be a letter"))?;
+        let mut rotor = Self::new(wiring, setting, notches);
+        rotor.set_ring(ring);
+        Ok(rotor)
     }
 }
 
@@ -44,14 +82,14 @@ impl Rotor {
             .chars().map(|c| (c as u8) - 97u8));
         let mut path_rev: [u8;26] = [0;26];
         // Find the reverse paths
-        for i in 0..26usize {
-            let fwd = path_fwd[i];
-            path_rev[fwd as usize] = i as u8;
+        for (i, fwd) in path_fwd.iter().enumerate() {
+            path_rev[*fwd as usize] = i as u8;
         }
         Self {
             path_fwd,
             path_rev,
             offset,
+            ring: 0,
             notches,
         }
     }
@@ -61,30 +99,63 @@ impl Rotor {
         self.offset = setting.to_ascii_lowercase() as u8 - 97u8;
     }
 
-    /// Step the rotor, returns true if the next rotor should step as well,
-    /// and false otherwise
-    pub fn step(&mut self) -> bool {
+    /// Change the ring setting of the rotor, where 'a' is the neutral ring setting
+    pub fn set_ring(&mut self, ring: char){
+        self.ring = ring.to_ascii_lowercase() as u8 - 97u8;
+    }
+
+    /// Ring setting of the rotor, as a letter where 'a' is the neutral ring setting
+    pub fn ring(&self) -> char {
+        (self.ring+97u8) as char
+    }
+
+    /// Current position of the rotor, as the letter shown in the window
+    pub fn position(&self) -> char {
+        (self.offset+97u8) as char
+    }
+
+    /// Step the rotor forward by one position
+    pub fn step(&mut self) {
         self.offset = (self.offset+1)%26;
-        // The -1 here is due to the way the rotor notches are normally described,
-        // the step occurs when stepping off the position rather than stepping onto it
-        if self.notches.contains(&(Self::wrap_26_sub(self.offset,1))) {
-            return true;
-        }
-        false
+    }
+
+    /// Check if the rotor is at a notch, in which case the pawl resting on it will engage
+    /// on the next key press, stepping the left neighbor rotor (and this rotor) as well.
+    /// Notches are described by the letter in the window when the turnover happens, so
+    /// rotor I with notch Q steps its neighbor when it moves from Q to R.
+    pub fn at_notch(&self) -> bool {
+        self.notches.contains(&self.offset)
     }
 
     /// Translate a character through the rotor
     pub fn translate_forward(&self, input: char) -> char {
         let input_val: u8 = input as u8 - 97u8;
-        let output_val: u8 = (input_val + self.offset) % 26;
-        ((Self::wrap_26_sub(self.path_fwd[output_val as usize], self.offset)) + 97u8) as char
+        let shift: u8 = self.shift();
+        let output_val: u8 = (input_val + shift) % 26;
+        ((Self::wrap_26_sub(self.path_fwd[output_val as usize], shift)) + 97u8) as char
     }
 
     /// Translate a character through the rotor on the reverse leg
     pub fn translate_reverse(&self, input: char) -> char {
         let input_val: u8 = input as u8-97u8;
-        let output_val: u8 = (input_val + self.offset) % 26;
-        ((Rotor::wrap_26_sub(self.path_rev[output_val as usize],self.offset))+97u8) as char
+        let shift: u8 = self.shift();
+        let output_val: u8 = (input_val + shift) % 26;
+        ((Rotor::wrap_26_sub(self.path_rev[output_val as usize],shift))+97u8) as char
+    }
+
+    /// The letter making up a string, if it is a single letter
+    fn single_letter(s: &str) -> Option<char> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_alphabetic() => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Effective rotation of the wiring, the ring setting moves the wiring
+    /// in the opposite direction to the rotor position
+    fn shift(&self) -> u8 {
+        Self::wrap_26_sub(self.offset, self.ring)
     }
 
     // Functions to create specific Rotors
@@ -128,6 +199,146 @@ impl Rotor {
         Self::new("FKQHTLXOCBJSPDZRAMEWNIUYGV", 'a', "ZM")
     }
 
+    /// Function to create the Beta Greek wheel of the M4, which never steps
+    pub fn new_beta() -> Self {
+        Self::new("LEYJVCNIXWPBQMDRTAKZGFUHOS", 'a', "")
+    }
+
+    /// Function to create the Gamma Greek wheel of the M4, which never steps
+    pub fn new_gamma() -> Self {
+        Self::new("FSOKANUERHMBTIYCWLQPZXVGJD", 'a', "")
+    }
+
+    /// Function to create rotor I of the commercial Enigma D and Enigma K
+    pub fn new_k_i() -> Self {
+        Self::new("LPGSZMHAEOQKVXRFYBUTNICJDW", 'a', "Y")
+    }
+
+    /// Function to create rotor II of the commercial Enigma D and Enigma K
+    pub fn new_k_ii() -> Self {
+        Self::new("SLVGBTFXJQOHEWIRZYAMKPCNDU", 'a', "E")
+    }
+
+    /// Function to create rotor III of the commercial Enigma D and Enigma K
+    pub fn new_k_iii() -> Self {
+        Self::new("CJGDPSHKTURAWZXFMYNQOBVLIE", 'a', "N")
+    }
+
+    /// Function to create rotor I of the Swiss Enigma K
+    pub fn new_swiss_k_i() -> Self {
+        Self::new("PEZUOHXSCVFMTBGLRINQJWAYDK", 'a', "Y")
+    }
+
+    /// Function to create rotor II of the Swiss Enigma K
+    pub fn new_swiss_k_ii() -> Self {
+        Self::new("ZOUESYDKFWPCIQXHMVBLGNJRAT", 'a', "E")
+    }
+
+    /// Function to create rotor III of the Swiss Enigma K
+    pub fn new_swiss_k_iii() -> Self {
+        Self::new("EHRVXGAOBQUSIMZFLYNWKTPDJC", 'a', "N")
+    }
+
+    /// Function to create rotor I of the Abwehr Enigma G-312, with 17 notches
+    pub fn new_g312_i() -> Self {
+        Self::new("DMTWSILRUYQNKFEJCAZBPGXOHV", 'a', "SUVWZABCEFGIKLOPQ")
+    }
+
+    /// Function to create rotor II of the Abwehr Enigma G-312, with 15 notches
+    pub fn new_g312_ii() -> Self {
+        Self::new("HQZGPJTMOBLNCIFDYAWVEUSRKX", 'a', "STVYZACDFGHKMNQ")
+    }
+
+    /// Function to create rotor III of the Abwehr Enigma G-312, with 11 notches
+    pub fn new_g312_iii() -> Self {
+        Self::new("UQNTLSZFMREHDPXKIBVYGJCWOA", 'a', "UWXAEFHKMNR")
+    }
+
+    /// Function to create rotor I of the Enigma G-260, with 17 notches
+    pub fn new_g260_i() -> Self {
+        Self::new("RCSPBLKQAUMHWYTIFZVGOJNEXD", 'a', "SUVWZABCEFGIKLOPQ")
+    }
+
+    /// Function to create rotor II of the Enigma G-260, with 15 notches
+    pub fn new_g260_ii() -> Self {
+        Self::new("WCMIBVPJXAROSGNDLZKEYHUFQT", 'a', "STVYZACDFGHKMNQ")
+    }
+
+    /// Function to create rotor III of the Enigma G-260, with 11 notches
+    pub fn new_g260_iii() -> Self {
+        Self::new("FVDHZELSQMAXOKYIWPGCBUJTNR", 'a', "UWXAEFHKMNR")
+    }
+
+    /// Function to create rotor I of the Enigma G-31, with 17 notches
+    pub fn new_g31_i() -> Self {
+        Self::new("LPGSZMHAEOQKVXRFYBUTNICJDW", 'a', "SUVWZABCEFGIKLOPQ")
+    }
+
+    /// Function to create rotor II of the Enigma G-31, with 15 notches
+    pub fn new_g31_ii() -> Self {
+        Self::new("SLVGBTFXJQOHEWIRZYAMKPCNDU", 'a', "STVYZACDFGHKMNQ")
+    }
+
+    /// Function to create rotor III of the Enigma G-31, with 11 notches
+    pub fn new_g31_iii() -> Self {
+        Self::new("CJGDPSHKTURAWZXFMYNQOBVLIE", 'a', "UWXAEFHKMNR")
+    }
+
+    /// Function to create rotor I of the Railway Enigma
+    pub fn new_railway_i() -> Self {
+        Self::new("JGDQOXUSCAMIFRVTPNEWKBLZYH", 'a', "N")
+    }
+
+    /// Function to create rotor II of the Railway Enigma
+    pub fn new_railway_ii() -> Self {
+        Self::new("NTZPSFBOKMWRCJDIVLAEYUXHGQ", 'a', "E")
+    }
+
+    /// Function to create rotor III of the Railway Enigma
+    pub fn new_railway_iii() -> Self {
+        Self::new("JVIUBHTCDYAKEQZPOSGXNRMWFL", 'a', "Y")
+    }
+
+    /// Function to create rotor I of the Enigma T, with 5 notches
+    pub fn new_t_i() -> Self {
+        Self::new("KPTYUELOCVGRFQDANJMBSWHZXI", 'a', "WZEKQ")
+    }
+
+    /// Function to create rotor II of the Enigma T, with 5 notches
+    pub fn new_t_ii() -> Self {
+        Self::new("UPHZLWEQMTDJXCAKSOIGVBYFNR", 'a', "WZFLR")
+    }
+
+    /// Function to create rotor III of the Enigma T, with 5 notches
+    pub fn new_t_iii() -> Self {
+        Self::new("QUDLYRFEKONVZAXWHMGPJBSICT", 'a', "WZEKQ")
+    }
+
+    /// Function to create rotor IV of the Enigma T, with 5 notches
+    pub fn new_t_iv() -> Self {
+        Self::new("CIWTBKXNRESPFLYDAGVHQUOJZM", 'a', "WZFLR")
+    }
+
+    /// Function to create rotor V of the Enigma T, with 5 notches
+    pub fn new_t_v() -> Self {
+        Self::new("UAXGISNJBVERDYLFZWTPCKOHMQ", 'a', "YCFKR")
+    }
+
+    /// Function to create rotor VI of the Enigma T, with 5 notches
+    pub fn new_t_vi() -> Self {
+        Self::new("XFUZGALVHCNYSEWQTDMRBKPIOJ", 'a', "XEIMQ")
+    }
+
+    /// Function to create rotor VII of the Enigma T, with 5 notches
+    pub fn new_t_vii() -> Self {
+        Self::new("BJVFTXPLNAYOZIKWGDQERUCHSM", 'a', "YCFKR")
+    }
+
+    /// Function to create rotor VIII of the Enigma T, with 5 notches
+    pub fn new_t_viii() -> Self {
+        Self::new("YMTPNZHWKODAJXELUQVGCBISFR", 'a', "XEIMQ")
+    }
+
     fn wrap_26_sub(lhs:u8, rhs:u8)->u8{
         if rhs <= lhs {
             lhs - rhs
@@ -167,15 +378,46 @@ mod test_rotors{
         assert_eq!('n', test_rotor.translate_forward('k'));
         assert_eq!('l', test_rotor.translate_reverse('t'));
         // Step the rotor
-        _ = test_rotor.step();
+        test_rotor.step();
         // Check the translation again
         assert_eq!('j', test_rotor.translate_forward('a'));
     }
 
+    #[test]
+    fn test_ring_setting(){
+        let mut test_rotor = Rotor::new_i();
+        test_rotor.set_ring('b');
+        // With ring setting B, A is wired to K rather than E
+        assert_eq!('k', test_rotor.translate_forward('a'));
+        assert_eq!('a', test_rotor.translate_reverse('k'));
+        // Moving the ring and position together leaves the wiring unchanged
+        test_rotor.set('b');
+        assert_eq!('e', test_rotor.translate_forward('a'));
+    }
+
     #[test]
     fn test_notch(){
         let mut test_rotor = Rotor::new_i();
-        test_rotor.offset = 16;
-        assert!(test_rotor.step());
+        test_rotor.offset = 15;
+        assert!(!test_rotor.at_notch());
+        test_rotor.step();
+        assert!(test_rotor.at_notch());
+        assert_eq!('q', test_rotor.position());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse() {
+        let mut rotor = Rotor::new_vi();
+        rotor.set('q');
+        rotor.set_ring('c');
+        assert_eq!(rotor, rotor.to_string().parse::<Rotor>().ok().unwrap());
+        assert_eq!(Rotor::new_beta(), Rotor::new_beta().to_string().parse::<Rotor>().ok().unwrap());
+        let error = |s: &str| s.parse::<Rotor>().err().unwrap().to_string();
+        assert_eq!("Invalid Rotor: Notches: q (expected Wire Configuration)", error("Notches: q"));
+        assert_eq!("Invalid Rotor: Wire Configuration: abc, Notches: q, Ring Setting: a, \
+        Current Setting: a (wiring must contain each letter once)",
+                   error("Wire Configuration: abc, Notches: q, Ring Setting: a, Current Setting: a"));
+        assert!(error(&Rotor::new_i().to_string().replace("Current Setting: a", "Current Setting: 1"))
+            .ends_with("(current setting must be a letter)"));
+    }
+}
@@ -0,0 +1,119 @@
+use crate::machine::enigma::EnigmaError;
+use crate::machine::text::{self, NonLetterPolicy, TranslateOptions};
+
+/// Common shape of rotor cipher machines, which encipher one letter per key press and
+/// move their rotors as they go
+pub trait RotorMachine {
+    /// Name of the machine
+    fn name(&self) -> String;
+
+    /// Press the key for a lowercase letter (a-z), stepping the rotors and returning the
+    /// enciphered letter
+    fn press(&mut self, letter: char) -> char;
+
+    /// Step the rotors once without enciphering anything
+    fn step(&mut self);
+
+    /// Current state of the machine, as the letters visible in the rotor windows
+    /// from left to right
+    fn state(&self) -> String;
+
+    /// Translate a string through the machine. Letters are enciphered (as lowercase), and
+    /// everything else passes through unchanged without stepping the rotors.
+    fn translate(&mut self, input: &str) -> String {
+        input.chars()
+            .map(|c| if c.is_ascii_alphabetic() { self.press(c.to_ascii_lowercase()) } else { c })
+            .collect()
+    }
+
+    /// Translate a string through the machine, with the handling of characters outside A-Z
+    /// and of letter case given by the options. Fails on the first character outside A-Z
+    /// when the policy is `NonLetterPolicy::Error`, giving its (1-based) position, except for
+    /// line breaks which pass through.
+    fn translate_with(&mut self, input: &str, options: &TranslateOptions) -> Result<String, EnigmaError> {
+        let mut output = String::new();
+        for (idx, c) in input.chars().enumerate() {
+            let letters = if c.is_ascii_alphabetic() {
+                c.encode_utf8(&mut [0; 4]).to_ascii_lowercase()
+            } else {
+                match (options.non_letters, text::transliterate(c)) {
+                    (NonLetterPolicy::Transliterate, Some(letters)) => letters.to_string(),
+                    (NonLetterPolicy::PassThrough, _) | (NonLetterPolicy::Transliterate, None) => {
+                        output.push(c);
+                        continue;
+                    }
+                    (NonLetterPolicy::Drop, _) => { continue; }
+                    (NonLetterPolicy::Error, _) if c == '\n' || c == '\r' => {
+                        output.push(c);
+                        continue;
+                    }
+                    (NonLetterPolicy::Error, _) => {
+                        return Err(EnigmaError::InvalidCharacter(
+                            format!("{:?} at position {}", c, idx + 1)));
+                    }
+                }
+            };
+            let uppercase = options.preserve_case && c.is_uppercase();
+            for letter in letters.chars() {
+                let translated = self.press(letter);
+                output.push(if uppercase { translated.to_ascii_uppercase() } else { translated });
+            }
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod test_rotor_machine {
+    use super::*;
+    use crate::machine::enigma::Enigma;
+    use crate::machine::typex::Typex;
+
+    #[test]
+    fn test_machines() {
+        let machines: Vec<Box<dyn RotorMachine>> = vec![Box::new(Enigma::default()),
+                                                        Box::new(Typex::default())];
+        for mut machine in machines {
+            let start = machine.state();
+            let encoded = machine.translate("Rotor Machines!");
+            assert_eq!(15, encoded.len());
+            assert!(encoded.ends_with('!'));
+            assert_ne!(start, machine.state());
+        }
+        assert_eq!("Enigma EnigmaI", Enigma::default().name());
+        // The default options behave like translate
+        assert_eq!(Enigma::default().translate("Rotor Machines!"),
+                   Enigma::default().translate_with("Rotor Machines!", &TranslateOptions::default())
+                       .ok().unwrap());
+        assert_eq!("aab", {
+            let mut machine = Enigma::default();
+            RotorMachine::step(&mut machine);
+            machine.state()
+        });
+    }
+
+    #[test]
+    fn test_translate_with() {
+        let options = |non_letters: NonLetterPolicy, preserve_case: bool| TranslateOptions {
+            non_letters,
+            preserve_case,
+        };
+        let mut machine = Enigma::default();
+        let encoded = machine.translate_with("Grüße, Herr Müller!",
+                                             &options(NonLetterPolicy::Transliterate, true)).ok().unwrap();
+        assert_eq!(22, encoded.len());
+        assert_eq!("Gruesse, Herr Mueller!",
+                   Enigma::default().translate_with(&encoded, &options(NonLetterPolicy::PassThrough, true))
+                       .ok().unwrap());
+        assert_eq!(Enigma::default().translate("abc"),
+                   Enigma::default().translate_with("a b-c", &options(NonLetterPolicy::Drop, false))
+                       .ok().unwrap());
+        let error = Enigma::default().translate_with("abc d", &options(NonLetterPolicy::Error, false));
+        assert_eq!("Invalid Character: ' ' at position 4", error.err().unwrap().to_string());
+        assert_eq!(Enigma::default().translate("ab\r\ncd\n"), Enigma::default()
+            .translate_with("ab\r\ncd\n", &options(NonLetterPolicy::Error, false)).ok().unwrap());
+        assert_eq!("aé", Typex::default()
+            .translate_with("aé", &options(NonLetterPolicy::PassThrough, false)).ok().unwrap()
+            .replace(|c: char| c.is_ascii_lowercase(), "a"));
+    }
+}
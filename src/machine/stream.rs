@@ -0,0 +1,141 @@
+use crate::machine::enigma::Enigma;
+use crate::machine::rotor_machine::RotorMachine;
+use std::io;
+use std::io::{Read, Write};
+
+/// Bytes enciphered at a time by `EnigmaWriter`
+const CHUNK_SIZE: usize = 4096;
+
+/// Pass a byte through the machine in the same way as `Enigma::translate`: ASCII letters are
+/// enciphered as lowercase, anything else (including the bytes of non-ASCII characters)
+/// passes through without stepping the rotors
+fn translate_byte(machine: &mut Enigma, byte: u8) -> u8 {
+    if byte.is_ascii_alphabetic() {
+        machine.press(byte.to_ascii_lowercase() as char) as u8
+    } else {
+        byte
+    }
+}
+
+/// Reader which enciphers (or deciphers) the bytes read from an inner reader. The machine
+/// keeps its state from one read to the next, so a stream read in chunks gives the same
+/// result as translating it in one go.
+pub struct EnigmaReader<R: Read> {
+    inner: R,
+    machine: Enigma,
+}
+
+impl<R: Read> EnigmaReader<R> {
+    /// Wrap a reader, translating what is read from it with the machine
+    pub fn new(inner: R, machine: Enigma) -> Self {
+        Self {
+            inner,
+            machine,
+        }
+    }
+
+    /// The machine, in its state after everything read so far
+    pub fn machine(&self) -> &Enigma {
+        &self.machine
+    }
+
+    /// Unwrap the inner reader and the machine
+    pub fn into_inner(self) -> (R, Enigma) {
+        (self.inner, self.machine)
+    }
+}
+
+impl<R: Read> Read for EnigmaReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        for byte in buf[..count].iter_mut() {
+            *byte = translate_byte(&mut self.machine, *byte);
+        }
+        Ok(count)
+    }
+}
+
+/// Writer which enciphers (or deciphers) the bytes written to it before passing them on to
+/// an inner writer, a chunk at a time. The machine keeps its state from one write to the next.
+pub struct EnigmaWriter<W: Write> {
+    inner: W,
+    machine: Enigma,
+}
+
+impl<W: Write> EnigmaWriter<W> {
+    /// Wrap a writer, translating what is written to it with the machine
+    pub fn new(inner: W, machine: Enigma) -> Self {
+        Self {
+            inner,
+            machine,
+        }
+    }
+
+    /// The machine, in its state after everything written so far
+    pub fn machine(&self) -> &Enigma {
+        &self.machine
+    }
+
+    /// Unwrap the inner writer and the machine
+    pub fn into_inner(self) -> (W, Enigma) {
+        (self.inner, self.machine)
+    }
+}
+
+impl<W: Write> Write for EnigmaWriter<W> {
+    /// Translate and write up to one chunk of the buffer. The whole chunk is written to the
+    /// inner writer before returning, so the machine never gets ahead of the output.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut chunk = [0u8; CHUNK_SIZE];
+        let count = buf.len().min(CHUNK_SIZE);
+        for (translated, byte) in chunk.iter_mut().zip(&buf[..count]) {
+            *translated = translate_byte(&mut self.machine, *byte);
+        }
+        self.inner.write_all(&chunk[..count])?;
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test_stream {
+    use super::*;
+
+    #[test]
+    fn test_reader() {
+        let message = "Streams keep the rotors turning, über chunk boundaries! ".repeat(200);
+        let mut machine = Enigma::default();
+        _ = machine.set_rotors("qev");
+        let expected = machine.clone().translate(&message);
+        let mut reader = EnigmaReader::new(message.as_bytes(), machine);
+        let mut translated = Vec::new();
+        let mut chunk = [0u8; 7];
+        loop {
+            let count = reader.read(&mut chunk).ok().unwrap();
+            if count == 0 {
+                break;
+            }
+            translated.extend_from_slice(&chunk[..count]);
+        }
+        assert_eq!(expected, String::from_utf8(translated).ok().unwrap());
+    }
+
+    #[test]
+    fn test_writer() {
+        let message = "a".repeat(CHUNK_SIZE * 2 + 100);
+        let mut machine = Enigma::default();
+        let expected = machine.clone().translate(&message);
+        let mut writer = EnigmaWriter::new(Vec::new(), machine.clone());
+        for part in message.as_bytes().chunks(1000) {
+            writer.write_all(part).ok().unwrap();
+        }
+        writer.flush().ok().unwrap();
+        let (output, state) = writer.into_inner();
+        assert_eq!(expected.as_bytes(), &output[..]);
+        machine.translate(&message);
+        assert_eq!(machine.positions(), state.positions());
+    }
+}
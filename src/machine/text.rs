@@ -0,0 +1,59 @@
+/// What to do with characters outside A-Z when translating
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum NonLetterPolicy {
+    /// Copy them to the output unchanged, without stepping the rotors
+    #[default]
+    PassThrough,
+    /// Leave them out of the output
+    Drop,
+    /// Stop with an error giving the character and its position (line breaks pass through)
+    Error,
+    /// Write common accented letters out in A-Z (Ä to AE, ß to SS, É to E) and encipher
+    /// them, other characters pass through unchanged
+    Transliterate,
+}
+
+/// Options for translating text through a rotor machine
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TranslateOptions {
+    /// Handling of characters outside A-Z
+    pub non_letters: NonLetterPolicy,
+    /// Keep uppercase letters uppercase in the output, rather than lowercasing everything
+    pub preserve_case: bool,
+}
+
+/// Spelling of an accented letter in A-Z (in lowercase), if it is a known one
+pub fn transliterate(c: char) -> Option<&'static str> {
+    let letters = match c.to_lowercase().next()? {
+        'ä' => "ae",
+        'ö' => "oe",
+        'ü' => "ue",
+        'ß' => "ss",
+        'æ' => "ae",
+        'œ' => "oe",
+        'à' | 'á' | 'â' | 'ã' | 'å' => "a",
+        'ç' => "c",
+        'è' | 'é' | 'ê' | 'ë' => "e",
+        'ì' | 'í' | 'î' | 'ï' => "i",
+        'ñ' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ø' => "o",
+        'ù' | 'ú' | 'û' => "u",
+        'ý' | 'ÿ' => "y",
+        _ => { return None; }
+    };
+    Some(letters)
+}
+
+#[cfg(test)]
+mod test_text {
+    use super::*;
+
+    #[test]
+    fn test_transliterate() {
+        assert_eq!(Some("ae"), transliterate('Ä'));
+        assert_eq!(Some("ss"), transliterate('ß'));
+        assert_eq!(Some("e"), transliterate('é'));
+        assert_eq!(None, transliterate('7'));
+        assert_eq!(None, transliterate('a'));
+    }
+}
@@ -0,0 +1,222 @@
+use crate::machine::enigma::EnigmaError;
+use crate::machine::entry_wheel;
+use crate::machine::reflector;
+use crate::machine::rotor;
+use crate::machine::rotor_machine::RotorMachine;
+use std::fmt;
+
+/// Rotors for the Typex, as (name, wiring, notches). The service wirings of the Typex
+/// rotors were secret and changed regularly, so these are the published example (training)
+/// rotors of CyberChef's Typex operation, "Example 1" to "Example 8" as A to H, every one of
+/// them with seven notches.
+const TYPEX_ROTORS: [(&str, &str, &str); 8] = [
+    ("A", "MCYLPQUVRXGSAOWNBJEZDTFKHI", "BFHNQUW"),
+    ("B", "KHWENRCBISXJQGOFMAPVYZDLTU", "BFHNQUW"),
+    ("C", "BYPDZMGIKQCUSATREHOJNLFWXV", "BFHNQUW"),
+    ("D", "ZANJCGDLVHIXOBRPMSWQUKFYET", "BFHNQUW"),
+    ("E", "QXBGUTOVFCZPJIHSWERYNDAMLK", "BFHNQUW"),
+    ("F", "BDCNWUEIQVFTSXALOGZJYMHKPR", "BFHNQUW"),
+    ("G", "WJUKEIABMSGFTQZVCNPHORDXYL", "BFHNQUW"),
+    ("H", "TNVCZXDIPFWQKHSJMAOYLEURGB", "BFHNQUW"),
+];
+
+/// Example reflector of the same set, pairing AN BC FG IE KD LU MH OR TS VZ WQ XJ YP
+const TYPEX_REFLECTOR: &str = "NCBKIGFMEXDUHARYWOTSLZQJPV";
+
+/// Number of rotors in the Typex, the two on the right are stators which never step
+const ROTOR_COUNT: usize = 5;
+const STATOR_COUNT: usize = 2;
+
+/// Struct representing the British Typex, with five rotors (three stepping rotors and two
+/// stators), multi-notch rotors, and a plugboard which need not be reciprocal
+#[derive(Clone)]
+pub struct Typex {
+    /// Names of the rotors placed in the machine
+    names: [String; ROTOR_COUNT],
+    /// Rotors placed in the machine, ordered from left (next to the reflector)
+    /// to right (next to the plugboard)
+    rotors: [rotor::Rotor; ROTOR_COUNT],
+    /// Plugboard, an arbitrary permutation of the letters on the way in which is
+    /// undone on the way out
+    plugboard: entry_wheel::EntryWheel,
+    /// Reflector in the machine
+    reflector: reflector::Reflector,
+}
+
+impl fmt::Display for Typex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Rotor Configuration:")?;
+        for (idx, (name, r)) in self.names.iter().zip(self.rotors.iter()).enumerate() {
+            if idx >= ROTOR_COUNT - STATOR_COUNT {
+                writeln!(f, "\tRotor {} ({}, Stator):{}", idx + 1, name, r)?;
+            } else {
+                writeln!(f, "\tRotor {} ({}):{}", idx + 1, name, r)?;
+            }
+        }
+        write!(f,
+               "Reflector Configuration:
+\t{}
+Plugboard Configuration:
+\t{}
+",
+               self.reflector,
+               self.plugboard
+        )
+    }
+}
+
+impl Default for Typex {
+    /// Create a default Typex configuration with rotors A,B,C,D,E and no plugboard wiring
+    fn default() -> Self {
+        let names = ["A", "B", "C", "D", "E"];
+        Self {
+            names: names.map(|name| name.to_string()),
+            rotors: names.map(|name| Self::rotor(name).expect("Default rotors are in the catalogue")),
+            plugboard: entry_wheel::EntryWheel::new_military(),
+            reflector: reflector::Reflector::new(TYPEX_REFLECTOR, 'a'),
+        }
+    }
+}
+
+impl Typex {
+    /// Names of the rotors which can be placed in the machine
+    pub fn rotor_names() -> Vec<&'static str> {
+        TYPEX_ROTORS.iter().map(|(name, _, _)| *name).collect()
+    }
+
+    /// Set a rotor in a given position, counting from the left. Positions 3 and 4 are the
+    /// stators, which take the same rotors but never step.
+    pub fn choose_rotor(&mut self, rotor: &str, position: u8) -> Result<(), EnigmaError> {
+        if position as usize >= ROTOR_COUNT {
+            return Err(EnigmaError::InvalidRotorPosition(position.to_string()));
+        }
+        match Self::rotor(rotor) {
+            Some(new_rotor) => {
+                self.names[position as usize] = rotor.to_ascii_uppercase();
+                self.rotors[position as usize] = new_rotor;
+                Ok(())
+            }
+            None => Err(EnigmaError::InvalidRotor(rotor.to_string())),
+        }
+    }
+
+    /// Change the rotor settings, one letter per rotor from the left (including the stators)
+    pub fn set_rotors(&mut self, setting: &str) -> Result<(), EnigmaError> {
+        if setting.chars().count() != ROTOR_COUNT || !setting.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(EnigmaError::InvalidRotorSetting(setting.to_string()));
+        }
+        for (r, c) in self.rotors.iter_mut().zip(setting.chars()) {
+            r.set(c);
+        }
+        Ok(())
+    }
+
+    /// Set the plugboard wiring, listing the letter each key is connected to, so
+    /// "BACDEF..." swaps A and B. Unlike the Enigma plugboard, the wiring doesn't need to
+    /// swap letters in pairs.
+    pub fn set_plugboard(&mut self, wiring: &str) -> Result<(), EnigmaError> {
+        if !entry_wheel::EntryWheel::is_valid_configuration(wiring) {
+            return Err(EnigmaError::InvalidPlugboardWire(wiring.to_string()));
+        }
+        // The entry wheel lists the key wired to each contact, so invert the wiring
+        let mut contacts: Vec<char> = vec!['a'; 26];
+        for (key, contact) in wiring.to_ascii_lowercase().chars().enumerate() {
+            contacts[(contact as u8 - 97u8) as usize] = (key as u8 + 97u8) as char;
+        }
+        self.plugboard = entry_wheel::EntryWheel::new(&contacts.iter().collect::<String>());
+        Ok(())
+    }
+
+    fn rotor(name: &str) -> Option<rotor::Rotor> {
+        TYPEX_ROTORS.iter()
+            .find(|(entry, _, _)| entry.eq_ignore_ascii_case(name))
+            .map(|(_, wiring, notches)| rotor::Rotor::new(wiring, 'a', notches))
+    }
+
+    /// Pass a single character through the Typex
+    fn translate_char(&self, input: char) -> char {
+        let mut transfer_char = self.plugboard.translate_forward(input);
+        for r in self.rotors.iter().rev() {
+            transfer_char = r.translate_forward(transfer_char);
+        }
+        transfer_char = self.reflector.translate(transfer_char);
+        for r in self.rotors.iter() {
+            transfer_char = r.translate_reverse(transfer_char);
+        }
+        self.plugboard.translate_reverse(transfer_char)
+    }
+}
+
+impl RotorMachine for Typex {
+    fn name(&self) -> String {
+        "Typex".to_string()
+    }
+
+    fn press(&mut self, letter: char) -> char {
+        self.step();
+        self.translate_char(letter)
+    }
+
+    /// Step the three leftmost rotors with pawls, in the same way as the Enigma, where
+    /// the rightmost of them always steps. The stators never move.
+    fn step(&mut self) {
+        let fast = ROTOR_COUNT - STATOR_COUNT - 1;
+        let mut to_step: [bool; ROTOR_COUNT] = [false; ROTOR_COUNT];
+        to_step[fast] = true;
+        for idx in 0..fast {
+            if self.rotors[idx + 1].at_notch() {
+                to_step[idx] = true;
+                to_step[idx + 1] = true;
+            }
+        }
+        for (r, step) in self.rotors.iter_mut().zip(to_step) {
+            if step {
+                r.step();
+            }
+        }
+    }
+
+    fn state(&self) -> String {
+        self.rotors.iter().map(|r| r.position()).collect()
+    }
+}
+
+#[cfg(test)]
+mod test_typex {
+    use super::*;
+
+    #[test]
+    fn test_translation() {
+        let mut test_encoder = Typex::default();
+        assert!(test_encoder.choose_rotor("H", 1).is_ok());
+        assert!(test_encoder.set_rotors("abcde").is_ok());
+        assert!(test_encoder.set_plugboard("BCAEDFGHIJKLMNOPQRSTUVWXYZ").is_ok());
+        let mut test_decoder = test_encoder.clone();
+        let encoded = test_encoder.translate("typex messages decode on the same machine");
+        assert_ne!("typex messages decode on the same machine", encoded);
+        assert_eq!("typex messages decode on the same machine", test_decoder.translate(&encoded));
+
+        // Known answer worked out separately from the wirings above, with a double step of
+        // rotor H on the first key
+        let mut test_machine = Typex::default();
+        _ = test_machine.choose_rotor("H", 1);
+        _ = test_machine.set_rotors("anmqc");
+        _ = test_machine.set_plugboard("BCAEDFGHIJKLMNOPQRSTUVWXYZ");
+        assert_eq!("ouhcovikpfpdrgovhtrmfnmdbwfivrzmnov",
+                   test_machine.translate("typexmessagesdecodeonthesamemachine"));
+    }
+
+    #[test]
+    fn test_step() {
+        let mut test_machine = Typex::default();
+        // Rotor C (the fast rotor) has seven notches, D and E are stators
+        _ = test_machine.set_rotors("acmqq");
+        test_machine.step();
+        assert_eq!("acnqq", test_machine.state());
+        _ = test_machine.set_rotors("acnqq");
+        test_machine.step();
+        assert_eq!("adoqq", test_machine.state());
+        assert!(test_machine.set_rotors("aaaa").is_err());
+        assert!(test_machine.choose_rotor("Z", 0).is_err());
+    }
+}
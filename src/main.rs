@@ -1,26 +1,49 @@
 use enigmars::machine;
 use enigmars::machine::enigma::Enigma;
+use enigmars::machine::model::Model;
+use std::env;
+use std::fs;
 use std::io;
 use std::io::Write;
+use std::process;
+
+mod cli;
 
 fn main() {
+    // Any arguments select the non-interactive mode
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        let result = cli::parse_args(&args).and_then(|options| cli::run(&options));
+        if let Err(err) = result {
+            eprintln!("{}", err);
+            process::exit(err.exit_code());
+        }
+        return;
+    }
     println!("Enigma Machine");
     // Create initial machine configuration
     let mut enigma_machine = machine::enigma::Enigma::default();
     loop {
         print!(
             "Please Select an Option:
-              1) Choose Rotors
-              2) Configure Rotors
-              3) Choose Reflector
-              4) Configure Reflector
-              5) Configure Plugboard
-              6) Display Configuration
-              7) Translate Message
-              8) Quit\n");
+              1) Choose Machine
+              2) Choose Rotors
+              3) Configure Rotors
+              4) Configure Ring Settings
+              5) Choose Reflector
+              6) Configure Reflector
+              7) Choose Entry Wheel
+              8) Configure Plugboard
+              9) Display Configuration
+              10) Translate Message
+              11) Load Configuration
+              12) Save Configuration
+              13) Quit\n");
         let mut choice = String::new();
-        // Get Choice
-        io::stdin().read_line(&mut choice).expect("Failed to read line");
+        // Get Choice, stopping at the end of input
+        if io::stdin().read_line(&mut choice).expect("Failed to read line") == 0 {
+            break;
+        }
 
         let choice: u32 = match choice.trim().parse() {
             Ok(num) => num,
@@ -28,18 +51,24 @@ fn main() {
         };
 
         match choice {
-            1 => { choose_rotors(&mut enigma_machine); }
-            2 => { configure_rotors(&mut enigma_machine); }
-            3 => { choose_reflector(&mut enigma_machine); }
-            4 => { configure_reflector(&mut enigma_machine);}
-            5 => { configure_plugboard(&mut enigma_machine);}
-            6 => {
-                println!("{}", enigma_machine);
-            }
-            7 => {
+            1 => { choose_machine(&mut enigma_machine); }
+            2 => { choose_rotors(&mut enigma_machine); }
+            3 => { configure_rotors(&mut enigma_machine); }
+            4 => { configure_rings(&mut enigma_machine); }
+            5 => { choose_reflector(&mut enigma_machine); }
+            6 => { configure_reflector(&mut enigma_machine);}
+            7 => { choose_entry_wheel(&mut enigma_machine);}
+            8 => { configure_plugboard(&mut enigma_machine);}
+            9 => {
+                println!("Key: {}", enigma_machine);
+                println!("{:#}", enigma_machine);
+            }
+            10 => {
                 translate_message(&mut enigma_machine.clone());
             }
-            8 => {
+            11 => { load_configuration(&mut enigma_machine); }
+            12 => { save_configuration(&enigma_machine); }
+            13 => {
                 println!("Exiting. Thank you!");
                 break;
             }
@@ -48,16 +77,44 @@ fn main() {
     }
 }
 
+// Choose which machine to use, this resets the configuration
+fn choose_machine(machine: &mut Enigma) {
+    loop {
+        let models: Vec<&str> = Model::ALL.iter().map(|m| m.name()).collect();
+        println!("Please enter choice of machine, this will reset the current configuration.
+        Choices are {} (M3 is accepted for the EnigmaI)", models.join(","));
+        let mut choice = String::new();
+        io::stdin().read_line(&mut choice).expect("Failed to get choice of machine");
+        if choice.trim().is_empty() {
+            break;
+        }
+        match choice.trim().parse::<Model>() {
+            Ok(model) => {
+                *machine = Enigma::from_model(model);
+                break;
+            }
+            Err(err) => {
+                println!("{}, please try again (or enter a blank string to return to main menu)", err);
+                continue;
+            }
+        }
+    }
+}
+
 // Choose which rotors to use
 fn choose_rotors(machine: &mut Enigma) {
     let mut finished = true;
     loop {
-        println!("Enter your choices of rotor seperated by commas.
-        choices are: I,II,III,IV,V,VI,VII,VIII");
+        let model = machine.model();
+        println!("Enter your choices of rotor seperated by commas, from left to right.
+        choices are: {}", model.rotor_names().join(","));
+        if !model.greek_wheel_names().is_empty() {
+            println!("        (first choose a Greek wheel: {})", model.greek_wheel_names().join(","));
+        }
         let mut choice = String::new();
         io::stdin().read_line(&mut choice).expect("Failed to get choice of rotor");
         for (idx, r) in choice.trim().split(",").enumerate() {
-            if idx > 2 {
+            if idx >= machine.rotor_count() {
                 println!("Too many rotors selected!");
                 finished = false;
                 break;
@@ -77,10 +134,17 @@ fn choose_rotors(machine: &mut Enigma) {
 fn choose_reflector(machine: &mut Enigma){
     loop {
         println!("Please enter choice for reflector.
-        Choices are a,b,c (case insensitive)");
+        Choices are {} (case insensitive)", machine.model().reflector_names().join(","));
+        if machine.model().has_rewirable_reflector() {
+            println!("        or D to plug the rewirable reflector (UKW-D)");
+        }
         let mut reflector = String::new();
         io::stdin().read_line(&mut reflector).expect("Failed to get choice for reflector");
-        if reflector.trim().len() == 0 {
+        if reflector.trim().is_empty() {
+            break;
+        }
+        if machine.model().has_rewirable_reflector() && reflector.trim().eq_ignore_ascii_case("d") {
+            rewire_reflector(machine);
             break;
         }
         match machine.choose_reflector(reflector.trim()) {
@@ -93,11 +157,57 @@ fn choose_reflector(machine: &mut Enigma){
     }
 }
 
+// Plug the rewirable reflector
+fn rewire_reflector(machine: &mut Enigma){
+    loop {
+        println!("Enter the 12 pairs of the reflector as a comma seperated list of <start>-<end>, \
+        in Bletchley Park notation. J-Y is wired inside the reflector and can't be plugged.");
+        let mut wires = String::new();
+        io::stdin().read_line(&mut wires).expect("Failed to read reflector wiring");
+        if wires.trim().is_empty() {
+            break;
+        }
+        match machine.rewire_reflector(wires.trim()) {
+            Ok(_) => {break;}
+            Err(err) => {
+                println!("{}, try again (or enter blank string to exit)", err);
+                continue;
+            }
+        }
+    }
+}
+
+fn choose_entry_wheel(machine: &mut Enigma){
+    loop {
+        println!("Please enter choice for entry wheel.
+        Choices are military, qwertzu, or a custom wiring of 26 letters listing the key \
+        wired to each contact of the wheel");
+        let mut entry_wheel = String::new();
+        io::stdin().read_line(&mut entry_wheel).expect("Failed to get choice for entry wheel");
+        let entry_wheel = entry_wheel.trim();
+        if entry_wheel.is_empty() {
+            break;
+        }
+        let result = if entry_wheel.len() == 26 {
+            machine.set_entry_wheel_wiring(entry_wheel)
+        } else {
+            machine.choose_entry_wheel(entry_wheel)
+        };
+        match result {
+            Ok(_) => {break;}
+            Err(err) => {
+                println!("{}, please try again (or enter a blank string to return to main menu)", err);
+                continue;
+            }
+        }
+    }
+}
+
 // Choose the settings for the rotors
 fn configure_rotors(machine: &mut Enigma) {
     loop {
-        println!("Enter your choice of rotor settings, should be 3 characters with no commas \
-        or other separation");
+        println!("Enter your choice of rotor settings, should be one character per rotor (including \
+        the Greek wheel) with no commas or other separation");
         let mut setting = String::new();
         io::stdin().read_line(&mut setting).expect("Failed to get choice of setting");
         match machine.set_rotors(setting.trim()) {
@@ -110,6 +220,23 @@ fn configure_rotors(machine: &mut Enigma) {
     }
 }
 
+// Choose the ring settings for the rotors
+fn configure_rings(machine: &mut Enigma) {
+    loop {
+        println!("Enter your choice of ring settings, should be one character per rotor (including \
+        the Greek wheel) with no commas or other separation (for example 'aaa' is ring setting 01-01-01)");
+        let mut setting = String::new();
+        io::stdin().read_line(&mut setting).expect("Failed to get choice of ring setting");
+        match machine.set_rings(setting.trim()) {
+            Ok(_) => { break; }
+            Err(err) => {
+                println!("{}", err);
+                continue;
+            }
+        };
+    }
+}
+
 // choose the setting for the reflector
 fn configure_reflector(machine: &mut Enigma){
     loop {
@@ -132,7 +259,10 @@ fn configure_plugboard(machine: &mut Enigma){
         print!("Please Select an Option
           1) Add Wires to the Plugboard
           2) Remove Wires from the Plugboard
-          3) Return to the Main Menu\n");
+          3) Attach the Uhr
+          4) Change the Uhr Setting
+          5) Detach the Uhr
+          6) Return to the Main Menu\n");
         io::stdout().flush().unwrap();
         let mut choice = String::new();
         io::stdin().read_line(&mut choice).expect("Failed to get choice in plugboard configuration");
@@ -144,7 +274,10 @@ fn configure_plugboard(machine: &mut Enigma){
         match choice {
             1=>{add_wires(machine);}
             2=>{remove_wires(machine);}
-            3=>{break;}
+            3=>{attach_uhr(machine);}
+            4=>{set_uhr(machine);}
+            5=>{machine.detach_uhr();}
+            6=>{break;}
             _=>{continue;}
         };
 
@@ -187,6 +320,109 @@ fn remove_wires(machine: &mut Enigma){
     }
 }
 
+// Attach the Uhr in place of the plugboard cables
+fn attach_uhr(machine: &mut Enigma){
+    loop{
+        println!("Enter the ten Uhr pairs as a comma seperated list of <a plug>-<b plug>, \
+        followed by the Uhr setting 00-39. For example 'a-b,c-d,e-f,g-h,i-j,k-l,m-n,o-p,q-r,s-t 27' \
+        plugs a with plug 1a and b with plug 1b, and so on.");
+        let mut uhr = String::new();
+        io::stdin().read_line(&mut uhr).expect("Failed to read Uhr pairs");
+        if uhr.trim().is_empty() {
+            break;
+        }
+        let (pairs, setting) = uhr.trim().rsplit_once(' ').unwrap_or((uhr.trim(), "00"));
+        let setting: u8 = match setting.trim().parse() {
+            Ok(setting) => setting,
+            Err(_) => {
+                println!("Invalid Uhr setting, try again (or enter blank string to exit)");
+                continue;
+            }
+        };
+        match machine.attach_uhr(pairs.trim(), setting){
+            Ok(_) => {break;}
+            Err(err) => {
+                println!("{}, try again (or enter blank string to exit)", err);
+                continue;
+            }
+        }
+    }
+}
+
+// Change the setting of the attached Uhr
+fn set_uhr(machine: &mut Enigma){
+    loop{
+        println!("Enter the new Uhr setting, 00-39");
+        let mut setting = String::new();
+        io::stdin().read_line(&mut setting).expect("Failed to read Uhr setting");
+        if setting.trim().is_empty() {
+            break;
+        }
+        let result = match setting.trim().parse::<u8>() {
+            Ok(setting) => machine.set_uhr(setting),
+            Err(_) => {
+                println!("Invalid Uhr setting, try again (or enter blank string to exit)");
+                continue;
+            }
+        };
+        match result {
+            Ok(_) => {break;}
+            Err(err) => {
+                println!("{}, try again (or enter blank string to exit)", err);
+                continue;
+            }
+        }
+    }
+}
+
+// Load the machine from a configuration file
+fn load_configuration(machine: &mut Enigma){
+    loop {
+        println!("Enter the path of the configuration file to load");
+        let mut path = String::new();
+        io::stdin().read_line(&mut path).expect("Failed to read configuration path");
+        if path.trim().is_empty() {
+            break;
+        }
+        let config = match fs::read_to_string(path.trim()) {
+            Ok(config) => config,
+            Err(err) => {
+                println!("Couldn't read {}: {}, try again (or enter blank string to exit)", path.trim(), err);
+                continue;
+            }
+        };
+        match Enigma::from_config(&config) {
+            Ok(loaded) => {
+                *machine = loaded;
+                break;
+            }
+            Err(err) => {
+                println!("{}, try again (or enter blank string to exit)", err);
+                continue;
+            }
+        }
+    }
+}
+
+// Save the machine to a configuration file
+fn save_configuration(machine: &Enigma){
+    loop {
+        println!("Enter the path of the configuration file to save");
+        let mut path = String::new();
+        io::stdin().read_line(&mut path).expect("Failed to read configuration path");
+        if path.trim().is_empty() {
+            break;
+        }
+        match fs::write(path.trim(), machine.to_config()) {
+            Ok(_) => {break;}
+            Err(err) => {
+                println!("Couldn't write {}: {}, try again (or enter blank string to exit)", path.trim(), err);
+                continue;
+            }
+        }
+    }
+}
+
 // translate message
 fn translate_message(machine: &mut Enigma){
     println!("Enter the message you would like to translate:");
@@ -0,0 +1,190 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Spelled out digits, with ZWO in place of ZWEI so it can't be mistaken for DREI
+const DIGITS: [&str; 10] = ["null", "eins", "zwo", "drei", "vier", "fuenf", "sechs", "sieben",
+    "acht", "neun"];
+/// Groups on each line of formatted ciphertext
+const GROUPS_PER_LINE: usize = 10;
+
+/// Conventions used to prepare plaintext for enciphering
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Convention {
+    /// Army and Luftwaffe (Heer and Luftwaffe) traffic, sent in five letter groups
+    Army,
+    /// Naval (Kriegsmarine) traffic, which also replaces CH with Q and is sent in four
+    /// letter groups
+    Navy,
+}
+
+impl fmt::Display for Convention {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Convention::Army => write!(f, "army"),
+            Convention::Navy => write!(f, "navy"),
+        }
+    }
+}
+
+impl FromStr for Convention {
+    type Err = FormattingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "army" | "heer" | "luftwaffe" => Ok(Convention::Army),
+            "navy" | "kriegsmarine" => Ok(Convention::Navy),
+            _ => Err(FormattingError::InvalidConvention(s.to_string())),
+        }
+    }
+}
+
+impl Convention {
+    /// Letters per group of the ciphertext
+    pub fn group_size(&self) -> usize {
+        match self {
+            Convention::Army => 5,
+            Convention::Navy => 4,
+        }
+    }
+}
+
+/// Prepare plaintext for enciphering. Umlauts and ß are written out (Ä to AE, ß to SS),
+/// digits are spelled out (23 to ZWODREI), and spaces and punctuation become X, with runs
+/// of them written as a single X. Naval traffic also replaces CH with Q. Any other
+/// characters are dropped, and the result is in lowercase.
+pub fn preprocess(plaintext: &str, convention: Convention) -> String {
+    let mut text = String::new();
+    let mut separated = true;
+    for c in plaintext.chars() {
+        let letters: &str = match c.to_lowercase().next().unwrap_or(c) {
+            'ä' => "ae",
+            'ö' => "oe",
+            'ü' => "ue",
+            'ß' => "ss",
+            c if c.is_ascii_lowercase() => {
+                text.push(c);
+                separated = false;
+                continue;
+            }
+            c if c.is_ascii_digit() => DIGITS[c as usize - '0' as usize],
+            c if c.is_whitespace() || c.is_ascii_punctuation() => {
+                if !separated {
+                    text.push('x');
+                    separated = true;
+                }
+                continue;
+            }
+            _ => { continue; }
+        };
+        text.push_str(letters);
+        separated = false;
+    }
+    if convention == Convention::Navy {
+        text = text.replace("ch", "q");
+    }
+    text
+}
+
+/// Reverse the preprocessing of a deciphered text as far as possible. X becomes a space
+/// (so full stops can't be told apart from spaces), words made up only of spelled out
+/// digits become numbers, and for naval traffic Q becomes CH again.
+pub fn postprocess(deciphered: &str, convention: Convention) -> String {
+    let mut text = deciphered.to_ascii_lowercase();
+    if convention == Convention::Navy {
+        text = text.replace('q', "ch");
+    }
+    text.split('x')
+        .filter(|word| !word.is_empty())
+        .map(|word| spelled_number(word).unwrap_or_else(|| word.to_string()))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Digits spelled out by a word, if it is made up only of spelled out digits
+fn spelled_number(word: &str) -> Option<String> {
+    let mut number = String::new();
+    let mut rest = word;
+    while !rest.is_empty() {
+        let (digit, spelled) = DIGITS.iter().enumerate()
+            .find(|(_, spelled)| rest.starts_with(*spelled))?;
+        number.push((b'0' + digit as u8) as char);
+        rest = &rest[spelled.len()..];
+    }
+    Some(number)
+}
+
+/// Split ciphertext into uppercase groups of letters, ten groups to a line, with the count
+/// of groups so far at the end of each line. Characters other than letters are dropped.
+pub fn format_groups(ciphertext: &str, group_size: usize) -> String {
+    let letters: Vec<char> = ciphertext.chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let groups: Vec<String> = letters.chunks(group_size.max(1))
+        .map(|group| group.iter().collect())
+        .collect();
+    let line_width = GROUPS_PER_LINE * (group_size + 1) - 1;
+    groups.chunks(GROUPS_PER_LINE).enumerate()
+        .map(|(line, chunk)| format!("{:line_width$}  {:3}", chunk.join(" "),
+                                     line * GROUPS_PER_LINE + chunk.len(),
+                                     line_width = line_width))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Collect the letters of ciphertext formatted by `format_groups` in lowercase, leaving out
+/// the group counts
+pub fn parse_groups(formatted: &str) -> String {
+    formatted.chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Errors of message formatting
+#[derive(Debug)]
+pub enum FormattingError {
+    InvalidConvention(String),
+}
+
+impl fmt::Display for FormattingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FormattingError::InvalidConvention(s) => { write!(f, "Invalid Convention: {}", s) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_formatting {
+    use super::*;
+
+    #[test]
+    fn test_preprocess() {
+        assert_eq!("feindxinxplanquadratxzwodreixsiebenxsichtbarx",
+                   preprocess("Feind in Planquadrat 23 / 7 sichtbar.", Convention::Army));
+        assert_eq!("gruessexausxmuenqenxseqsxuhrx",
+                   preprocess("Grüße aus München, 6 Uhr!", Convention::Navy));
+        assert_eq!("ueberfallxzwonullnullxstrasse", preprocess(" Überfall: 200 Straße", Convention::Army));
+    }
+
+    #[test]
+    fn test_postprocess() {
+        assert_eq!("feind in planquadrat 23 7 sichtbar",
+                   postprocess("feindxinxplanquadratxzwodreixsiebenxsichtbarx", Convention::Army));
+        assert_eq!("gruesse aus muenchen 6 uhr",
+                   postprocess("gruessexausxmuenqenxseqsxuhrx", Convention::Navy));
+        // Words which only start with a digit are left alone
+        assert_eq!("einsatz", postprocess("einsatz", Convention::Army));
+    }
+
+    #[test]
+    fn test_groups() {
+        let formatted = format_groups("abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyzab", 5);
+        let lines: Vec<&str> = formatted.lines().collect();
+        assert_eq!(vec!["ABCDE FGHIJ KLMNO PQRST UVWXY ZABCD EFGHI JKLMN OPQRS TUVWX   10",
+                        &format!("YZAB{}11", " ".repeat(58))], lines);
+        assert_eq!(format!("ABCD EFGH I{}3", " ".repeat(42)), format_groups("abcd efgh i", 4));
+        assert_eq!("abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyzab", parse_groups(&formatted));
+    }
+}
@@ -0,0 +1,298 @@
+use crate::machine::enigma::{Enigma, EnigmaError};
+use std::fmt;
+use std::str::FromStr;
+
+/// Letters per group of the transmitted text
+const GROUP_SIZE: usize = 5;
+/// Letters in the letter group carrying the Kenngruppe (two filler letters and a Kenngruppe)
+const KENNGRUPPE_GROUP_SIZE: usize = 5;
+/// Letters of a doubled indicator when a message is parsed, a doubled three letter key
+const DOUBLED_INDICATOR_SIZE: usize = 6;
+
+/// Procedure used to send the message key (Spruchschlüssel) along with a message
+#[derive(Clone, PartialEq, Debug)]
+pub enum Procedure {
+    /// Procedure used until May 1940. The message key is enciphered twice with the rotors
+    /// at the Grundstellung of the key sheet, and the doubled indicator starts the text.
+    Doubled,
+    /// Procedure used from May 1940. The operator picks a Grundstellung which is sent in
+    /// clear in the header, followed by the message key enciphered once at it. The text
+    /// starts with a letter group of two filler letters and a Kenngruppe from the key
+    /// sheet, for example "dhwhm" for the Kenngruppe "whm".
+    Single { grundstellung: String, kenngruppe: String },
+}
+
+/// A complete message as sent, with its header and indicators. It is displayed in the form
+///
+/// ```text
+/// 1230 - 60 - QWE EWG -
+/// DHWHM XYZAB ...
+/// ```
+///
+/// where the header holds the time, the number of letters in the text, and with the single
+/// indicator procedure the Grundstellung and enciphered message key. With the doubled
+/// indicator procedure the header is only "1230 - 61 -", and the text starts with the
+/// doubled indicator.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Message {
+    /// Time the message was sent, for example "1230"
+    pub time: String,
+    /// Grundstellung sent in clear (single indicator procedure only)
+    pub grundstellung: Option<String>,
+    /// Enciphered message key, doubled with the doubled indicator procedure
+    pub indicator: String,
+    /// Letter group with the Kenngruppe (single indicator procedure only)
+    pub kenngruppe: Option<String>,
+    /// Enciphered text
+    pub ciphertext: String,
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = self.text().to_ascii_uppercase();
+        match &self.grundstellung {
+            Some(grundstellung) => writeln!(f, "{} - {} - {} {} -", self.time, text.len(),
+                                            grundstellung.to_ascii_uppercase(),
+                                            self.indicator.to_ascii_uppercase())?,
+            None => writeln!(f, "{} - {} -", self.time, text.len())?,
+        }
+        let groups: Vec<&str> = text.as_bytes().chunks(GROUP_SIZE)
+            .map(|group| std::str::from_utf8(group).expect("Text is ASCII letters"))
+            .collect();
+        writeln!(f, "{}", groups.join(" "))
+    }
+}
+
+impl FromStr for Message {
+    type Err = IndicatorError;
+
+    /// Parse a message from the form it is displayed in
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().map(|line| line.trim()).filter(|line| !line.is_empty());
+        let header = lines.next()
+            .ok_or_else(|| IndicatorError::InvalidMessage("empty message".to_string()))?;
+        let fields: Vec<&str> = header.split(" - ").map(|field| field.trim_end_matches(" -")).collect();
+        let (time, count, indicator) = match fields[..] {
+            [time, count] => (time, count, None),
+            [time, count, indicator] => (time, count, Some(indicator)),
+            _ => {
+                return Err(IndicatorError::InvalidMessage(
+                    format!("{} (expected <time> - <letters> - [<grundstellung> <indicator>] -)", header)));
+            }
+        };
+        let count: usize = count.parse()
+            .map_err(|_| IndicatorError::InvalidMessage(format!("{} (invalid letter count)", header)))?;
+        let text: String = lines.flat_map(|line| line.chars())
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if !text.chars().all(|c| c.is_ascii_lowercase()) {
+            return Err(IndicatorError::InvalidMessage("text must only contain letters".to_string()));
+        }
+        if text.len() != count {
+            return Err(IndicatorError::InvalidMessage(
+                format!("header gives {} letters but the text has {}", count, text.len())));
+        }
+        match indicator {
+            Some(indicator) => {
+                let Some((grundstellung, indicator)) = indicator.split_once(' ') else {
+                    return Err(IndicatorError::InvalidMessage(
+                        format!("{} (expected <grundstellung> <indicator>)", indicator)));
+                };
+                if text.len() < KENNGRUPPE_GROUP_SIZE {
+                    return Err(IndicatorError::InvalidMessage("missing Kenngruppe".to_string()));
+                }
+                Ok(Message {
+                    time: time.to_string(),
+                    grundstellung: Some(grundstellung.to_ascii_lowercase()),
+                    indicator: indicator.to_ascii_lowercase(),
+                    kenngruppe: Some(text[..KENNGRUPPE_GROUP_SIZE].to_string()),
+                    ciphertext: text[KENNGRUPPE_GROUP_SIZE..].to_string(),
+                })
+            }
+            None => {
+                // Deciphering splits the text again for machines with another number of rotors
+                let split = DOUBLED_INDICATOR_SIZE.min(text.len());
+                Ok(Message {
+                    time: time.to_string(),
+                    grundstellung: None,
+                    indicator: text[..split].to_string(),
+                    kenngruppe: None,
+                    ciphertext: text[split..].to_string(),
+                })
+            }
+        }
+    }
+}
+
+impl Message {
+    /// Text of the message as sent, including the Kenngruppe and doubled indicator
+    fn text(&self) -> String {
+        match &self.kenngruppe {
+            Some(kenngruppe) => format!("{}{}", kenngruppe, self.ciphertext),
+            None => format!("{}{}", self.indicator, self.ciphertext),
+        }
+    }
+}
+
+/// Encipher a message with a day key, using a message key chosen by the operator. With the
+/// doubled indicator procedure the rotors of the day key must be at the Grundstellung of
+/// the key sheet (as set up by `KeySheet::machine`). Characters other than letters are
+/// dropped from the plaintext.
+pub fn encipher(day_key: &Enigma, procedure: &Procedure, time: &str, message_key: &str,
+                plaintext: &str) -> Result<Message, IndicatorError> {
+    if !is_setting(day_key, message_key) {
+        return Err(IndicatorError::InvalidMessageKey(message_key.to_string()));
+    }
+    let mut machine = day_key.clone();
+    let (grundstellung, kenngruppe, indicator) = match procedure {
+        Procedure::Doubled => {
+            (None, None, machine.translate(&format!("{}{}", message_key, message_key)))
+        }
+        Procedure::Single { grundstellung, kenngruppe } => {
+            if !is_setting(day_key, grundstellung) {
+                return Err(IndicatorError::InvalidGrundstellung(grundstellung.to_string()));
+            }
+            if kenngruppe.len() != KENNGRUPPE_GROUP_SIZE
+                || !kenngruppe.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(IndicatorError::InvalidKenngruppe(kenngruppe.to_string()));
+            }
+            machine.set_rotors(grundstellung).map_err(IndicatorError::Enigma)?;
+            (Some(grundstellung.to_ascii_lowercase()), Some(kenngruppe.to_ascii_lowercase()),
+             machine.translate(message_key))
+        }
+    };
+    machine.set_rotors(message_key).map_err(IndicatorError::Enigma)?;
+    let letters: String = plaintext.chars().filter(|c| c.is_ascii_alphabetic()).collect();
+    Ok(Message {
+        time: time.to_string(),
+        grundstellung,
+        indicator,
+        kenngruppe,
+        ciphertext: machine.translate(&letters),
+    })
+}
+
+/// Decipher a message with a day key, giving the message key and the plaintext. Messages
+/// without a Grundstellung in the header use the doubled indicator procedure, for which the
+/// rotors of the day key must be at the Grundstellung of the key sheet.
+pub fn decipher(day_key: &Enigma, message: &Message) -> Result<(String, String), IndicatorError> {
+    let mut machine = day_key.clone();
+    let key_length = machine.rotor_count();
+    let (message_key, ciphertext) = match &message.grundstellung {
+        Some(grundstellung) => {
+            if !is_setting(day_key, grundstellung) {
+                return Err(IndicatorError::InvalidGrundstellung(grundstellung.to_string()));
+            }
+            machine.set_rotors(grundstellung).map_err(IndicatorError::Enigma)?;
+            (machine.translate(&message.indicator), message.ciphertext.clone())
+        }
+        None => {
+            // The doubled indicator is twice as long as the message key of the machine
+            let text = message.text();
+            if text.len() < 2 * key_length {
+                return Err(IndicatorError::InvalidMessage("missing indicator".to_string()));
+            }
+            let doubled = machine.translate(&text[..2 * key_length]);
+            if doubled[..key_length] != doubled[key_length..] {
+                return Err(IndicatorError::GarbledIndicator(
+                    format!("{} deciphers to {}", &text[..2 * key_length], doubled)));
+            }
+            (doubled[..key_length].to_string(), text[2 * key_length..].to_string())
+        }
+    };
+    if !is_setting(day_key, &message_key) {
+        return Err(IndicatorError::InvalidMessageKey(message_key.clone()));
+    }
+    machine.set_rotors(&message_key).map_err(IndicatorError::Enigma)?;
+    let plaintext = machine.translate(&ciphertext);
+    Ok((message_key, plaintext))
+}
+
+/// Check that a rotor setting has one letter per rotor of the machine
+fn is_setting(machine: &Enigma, setting: &str) -> bool {
+    setting.chars().count() == machine.rotor_count()
+        && setting.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+pub enum IndicatorError {
+    InvalidMessageKey(String),
+    InvalidGrundstellung(String),
+    InvalidKenngruppe(String),
+    InvalidMessage(String),
+    GarbledIndicator(String),
+    Enigma(EnigmaError),
+}
+
+impl fmt::Display for IndicatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IndicatorError::InvalidMessageKey(s) => { write!(f, "Invalid Message Key: {}", s) }
+            IndicatorError::InvalidGrundstellung(s) => { write!(f, "Invalid Grundstellung: {}", s) }
+            IndicatorError::InvalidKenngruppe(s) => { write!(f, "Invalid Kenngruppe: {}", s) }
+            IndicatorError::InvalidMessage(s) => { write!(f, "Invalid Message: {}", s) }
+            IndicatorError::GarbledIndicator(s) => { write!(f, "Garbled Indicator: {}", s) }
+            IndicatorError::Enigma(err) => { write!(f, "{}", err) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_indicator {
+    use super::*;
+
+    fn day_key() -> Enigma {
+        "B II-IV-V 02-21-12 WXC AV BS CG DL FU HZ IN KM OW RX".parse::<Enigma>().ok().unwrap()
+    }
+
+    #[test]
+    fn test_doubled_indicator() {
+        let message = encipher(&day_key(), &Procedure::Doubled, "1230", "kfh",
+                               "Angriff im Morgengrauen").ok().unwrap();
+        let mut machine = day_key();
+        assert_eq!(machine.translate("kfhkfh"), message.indicator);
+        let sent = message.to_string();
+        assert!(sent.starts_with("1230 - 27 -\n"));
+        assert_eq!(6 + 21, message.text().len());
+        let received = sent.parse::<Message>().ok().unwrap();
+        assert_eq!(message, received);
+        let (key, plaintext) = decipher(&day_key(), &received).ok().unwrap();
+        assert_eq!("kfh", key);
+        assert_eq!("angriffimmorgengrauen", plaintext);
+        // A corrupted indicator no longer deciphers to a doubled key
+        let mut garbled = received.clone();
+        let first = garbled.indicator.remove(0);
+        garbled.indicator.insert(0, if first == 'z' { 'a' } else { (first as u8 + 1) as char });
+        assert!(decipher(&day_key(), &garbled).err().unwrap().to_string()
+            .starts_with("Garbled Indicator"));
+    }
+
+    #[test]
+    fn test_single_indicator() {
+        let procedure = Procedure::Single { grundstellung: "qwe".to_string(),
+            kenngruppe: "dhwhm".to_string() };
+        let message = encipher(&day_key(), &procedure, "0915", "rtz", "Feind in Sicht").ok().unwrap();
+        let mut machine = day_key();
+        _ = machine.set_rotors("qwe");
+        assert_eq!(machine.translate("rtz"), message.indicator);
+        let sent = message.to_string();
+        assert!(sent.starts_with(&format!("0915 - 17 - QWE {} -\nDHWHM ",
+                                          message.indicator.to_ascii_uppercase())));
+        let received = sent.parse::<Message>().ok().unwrap();
+        assert_eq!(message, received);
+        let (key, plaintext) = decipher(&day_key(), &received).ok().unwrap();
+        assert_eq!("rtz", key);
+        assert_eq!("feindinsicht", plaintext);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |result: Result<Message, IndicatorError>| result.err().unwrap().to_string();
+        assert_eq!("Invalid Message Key: ab", error(encipher(&day_key(), &Procedure::Doubled, "1230", "ab", "")));
+        let procedure = Procedure::Single { grundstellung: "qwe".to_string(), kenngruppe: "whm".to_string() };
+        assert_eq!("Invalid Kenngruppe: whm", error(encipher(&day_key(), &procedure, "1230", "abc", "")));
+        assert_eq!("Invalid Message: header gives 7 letters but the text has 5",
+                   error("1230 - 7 - QWE ABC -\nDHWHM".parse::<Message>()));
+        assert!(error("1230 QWE".parse::<Message>()).starts_with("Invalid Message: 1230 QWE"));
+    }
+}
@@ -0,0 +1,380 @@
+use crate::machine::enigma::{Enigma, EnigmaError, SlotKind};
+use crate::machine::model::Model;
+use crate::random::Random;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+/// Number of plugboard cables used each day
+const PLUG_PAIRS: usize = 10;
+/// Number of Kenngruppen listed for each day
+const KENNGRUPPEN: usize = 4;
+/// Attempts at finding a rotor order which hasn't been used yet in the month
+const ROTOR_ORDER_ATTEMPTS: usize = 100;
+/// Column titles of the printed sheet
+const COLUMNS: [&str; 6] = ["Datum", "Walzenlage", "Ringstellung", "Steckerverbindungen",
+    "Grundstellung", "Kenngruppen"];
+
+/// Key for a single day of a key sheet
+#[derive(Clone, PartialEq, Debug)]
+pub struct DailyKey {
+    /// Day of the month
+    pub day: u8,
+    /// Rotor order (Walzenlage) from left to right, including the Greek wheel
+    pub rotors: Vec<String>,
+    /// Ring settings (Ringstellung) from left to right, 1-26
+    pub rings: Vec<u8>,
+    /// Plugboard connections (Steckerverbindungen), empty if the model has no plugboard
+    pub plugs: Vec<(char, char)>,
+    /// Basic setting (Grundstellung) the rotors are turned to before enciphering indicators
+    pub grundstellung: String,
+    /// Trigrams sent in the message header to identify the key of the day (Kenngruppen)
+    pub kenngruppen: Vec<String>,
+}
+
+/// A month of daily keys (Schlüsselblatt) for a model. The printed sheet lists the days
+/// from the last to the first, so the days which have been used can be cut off the bottom.
+#[derive(Clone, PartialEq, Debug)]
+pub struct KeySheet {
+    model: Model,
+    /// Reflector used for the whole month
+    reflector: String,
+    year: u16,
+    month: u8,
+    /// Seed the sheet was generated from, which generates the same sheet again
+    seed: u64,
+    /// Keys from the first day of the month to the last
+    days: Vec<DailyKey>,
+}
+
+impl fmt::Display for KeySheet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Enigma {} Schlüsselblatt {}-{:02}, Umkehrwalze {}, Seed {}",
+                 self.model, self.year, self.month, self.reflector, self.seed)?;
+        let rows: Vec<[String; 6]> = self.days.iter().rev().map(|key| [
+            format!("{:02}", key.day),
+            key.rotors.join(" "),
+            key.rings.iter().map(|ring| format!("{:02}", ring)).collect::<Vec<String>>().join(" "),
+            key.plugs.iter()
+                .map(|(a, b)| format!("{}{}", a, b).to_ascii_uppercase())
+                .collect::<Vec<String>>()
+                .join(" "),
+            key.grundstellung.to_ascii_uppercase(),
+            key.kenngruppen.join(" "),
+        ]).collect();
+        let widths: Vec<usize> = (0..COLUMNS.len())
+            .map(|col| rows.iter().map(|row| row[col].chars().count())
+                .chain([COLUMNS[col].chars().count()])
+                .max()
+                .unwrap_or(0))
+            .collect();
+        let line = |cells: Vec<&str>| -> String {
+            let padded: Vec<String> = cells.iter().zip(widths.iter())
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect();
+            padded.join(" | ").trim_end().to_string()
+        };
+        writeln!(f, "{}", line(COLUMNS.to_vec()))?;
+        for row in rows.iter() {
+            writeln!(f, "{}", line(row.iter().map(|cell| cell.as_str()).collect()))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for KeySheet {
+    type Err = KeySheetError;
+
+    /// Parse a key sheet from its printed form
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().map(|line| line.trim()).filter(|line| !line.is_empty());
+        let header = lines.next()
+            .ok_or_else(|| KeySheetError::InvalidSheet("empty key sheet".to_string()))?;
+        let invalid_header = || KeySheetError::InvalidSheet(format!("{} (expected \
+            Enigma <model> Schlüsselblatt <year>-<month>, Umkehrwalze <reflector>, Seed <seed>)",
+            header));
+        let (model, rest) = header.strip_prefix("Enigma ")
+            .and_then(|rest| rest.split_once(" Schlüsselblatt "))
+            .ok_or_else(invalid_header)?;
+        let model = model.parse::<Model>().map_err(KeySheetError::Enigma)?;
+        let fields: Vec<&str> = rest.split(", ").collect();
+        let [month, reflector, seed] = fields[..] else { return Err(invalid_header()); };
+        let (year, month) = month.split_once('-').ok_or_else(invalid_header)?;
+        let year: u16 = year.parse().map_err(|_| invalid_header())?;
+        let month: u8 = month.parse().map_err(|_| KeySheetError::InvalidMonth(month.to_string()))?;
+        let reflector = reflector.strip_prefix("Umkehrwalze ").ok_or_else(invalid_header)?;
+        let seed: u64 = seed.strip_prefix("Seed ")
+            .and_then(|seed| seed.parse().ok())
+            .ok_or_else(invalid_header)?;
+        if !(1..=12).contains(&month) {
+            return Err(KeySheetError::InvalidMonth(format!("{:02}", month)));
+        }
+        match lines.next() {
+            Some(columns) if columns.starts_with(COLUMNS[0]) => {}
+            _ => { return Err(KeySheetError::InvalidSheet("missing column titles".to_string())); }
+        }
+
+        let mut days: Vec<DailyKey> = Vec::new();
+        for row in lines {
+            let cells: Vec<&str> = row.split('|').map(|cell| cell.trim()).collect();
+            let [day, rotors, rings, plugs, grundstellung, kenngruppen] = cells[..] else {
+                return Err(KeySheetError::InvalidSheet(format!("{} (expected 6 columns)", row)));
+            };
+            let day: u8 = day.parse().map_err(|_| KeySheetError::InvalidDay(day.to_string()))?;
+            let rings = rings.split_whitespace()
+                .map(|ring| match ring.parse::<u8>() {
+                    Ok(ring) if (1..=26).contains(&ring) => Ok(ring),
+                    _ => Err(KeySheetError::Enigma(EnigmaError::InvalidRingSetting(
+                        format!("{} on day {:02}", ring, day)))),
+                })
+                .collect::<Result<Vec<u8>, KeySheetError>>()?;
+            let plugs = plugs.split_whitespace()
+                .map(|pair| {
+                    let letters: Vec<char> = pair.to_ascii_lowercase().chars().collect();
+                    match letters[..] {
+                        [a, b] if a.is_ascii_lowercase() && b.is_ascii_lowercase() => Ok((a, b)),
+                        _ => Err(KeySheetError::Enigma(EnigmaError::InvalidPlugboardWire(
+                            format!("{} on day {:02}", pair, day)))),
+                    }
+                })
+                .collect::<Result<Vec<(char, char)>, KeySheetError>>()?;
+            days.push(DailyKey {
+                day,
+                rotors: rotors.split_whitespace().map(String::from).collect(),
+                rings,
+                plugs,
+                grundstellung: grundstellung.to_ascii_lowercase(),
+                kenngruppen: kenngruppen.split_whitespace().map(String::from).collect(),
+            });
+        }
+        days.reverse();
+        let sheet = KeySheet {
+            model,
+            reflector: reflector.to_string(),
+            year,
+            month,
+            seed,
+            days,
+        };
+        // Every day must give a working machine
+        for key in sheet.days.iter() {
+            sheet.machine(key.day)?;
+        }
+        Ok(sheet)
+    }
+}
+
+impl KeySheet {
+    /// Generate the key sheet of a month for a model. The rotor order of each day uses
+    /// every rotor at most once, and repeats an earlier order of the month only if the
+    /// model has too few rotors. The Enigma I uses reflector B and the M4 the thin
+    /// reflector B, other models the reflector in their catalogue. Generating again with
+    /// the same model, month and seed gives the same sheet.
+    pub fn generate(model: Model, year: u16, month: u8, seed: u64) -> Result<Self, KeySheetError> {
+        if !(1..=12).contains(&month) {
+            return Err(KeySheetError::InvalidMonth(format!("{:02}", month)));
+        }
+        // Mix the month into the seed, so each month of a year differs
+        let month_index = year as u64 * 12 + month as u64;
+        let mut random = Random::new(seed ^ month_index.wrapping_mul(0x2545F4914F6CDD1D));
+        let reflector = match model {
+            Model::EnigmaI => "B",
+            Model::M4 => "B-thin",
+            _ => model.reflector_names()[0],
+        };
+        let mut used_orders: HashSet<Vec<String>> = HashSet::new();
+        let mut used_kenngruppen: HashSet<String> = HashSet::new();
+        let mut days: Vec<DailyKey> = Vec::new();
+        for day in 1..=days_in_month(year, month) {
+            let mut rotors = Self::rotor_order(model, &mut random);
+            for _ in 0..ROTOR_ORDER_ATTEMPTS {
+                if !used_orders.contains(&rotors) {
+                    break;
+                }
+                rotors = Self::rotor_order(model, &mut random);
+            }
+            used_orders.insert(rotors.clone());
+            let rings: Vec<u8> = rotors.iter().map(|_| random.below(26) as u8 + 1).collect();
+            let mut plugs: Vec<(char, char)> = Vec::new();
+            if model.has_plugboard() {
+                let mut letters: Vec<char> = ('a'..='z').collect();
+                random.shuffle(&mut letters);
+                plugs = letters.chunks(2).take(PLUG_PAIRS)
+                    .map(|pair| (pair[0].min(pair[1]), pair[0].max(pair[1])))
+                    .collect();
+                plugs.sort();
+            }
+            let grundstellung: String = rotors.iter().map(|_| random.letter()).collect();
+            let mut kenngruppen: Vec<String> = Vec::new();
+            while kenngruppen.len() < KENNGRUPPEN {
+                let group: String = (0..3).map(|_| random.letter()).collect();
+                if used_kenngruppen.insert(group.clone()) {
+                    kenngruppen.push(group);
+                }
+            }
+            days.push(DailyKey {
+                day,
+                rotors,
+                rings,
+                plugs,
+                grundstellung,
+                kenngruppen,
+            });
+        }
+        Ok(Self {
+            model,
+            reflector: reflector.to_string(),
+            year,
+            month,
+            seed,
+            days,
+        })
+    }
+
+    /// Pick a rotor order for the slots of the model, with no rotor used twice
+    fn rotor_order(model: Model, random: &mut Random) -> Vec<String> {
+        let mut rotors = model.rotor_names();
+        random.shuffle(&mut rotors);
+        let mut rotors = rotors.into_iter();
+        let greek_wheels = model.greek_wheel_names();
+        model.slots().iter()
+            .map(|kind| match kind {
+                SlotKind::Greek => {
+                    greek_wheels[random.below(greek_wheels.len())].to_string()
+                }
+                _ => rotors.next().expect("Models have a rotor for every slot").to_string(),
+            })
+            .collect()
+    }
+
+    /// Model the sheet is for
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Reflector used for the whole month
+    pub fn reflector(&self) -> &str {
+        &self.reflector
+    }
+
+    /// Year and month of the sheet
+    pub fn month(&self) -> (u16, u8) {
+        (self.year, self.month)
+    }
+
+    /// Seed the sheet was generated from
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Keys of the sheet, from the first day of the month to the last
+    pub fn days(&self) -> &[DailyKey] {
+        &self.days
+    }
+
+    /// Key for a day of the month
+    pub fn key(&self, day: u8) -> Option<&DailyKey> {
+        self.days.iter().find(|key| key.day == day)
+    }
+
+    /// Day whose key is identified by a Kenngruppe
+    pub fn day_for_kenngruppe(&self, kenngruppe: &str) -> Option<u8> {
+        self.days.iter()
+            .find(|key| key.kenngruppen.iter().any(|group| group.eq_ignore_ascii_case(kenngruppe)))
+            .map(|key| key.day)
+    }
+
+    /// Configure a machine with the key of a day, with the rotors turned to the Grundstellung
+    pub fn machine(&self, day: u8) -> Result<Enigma, KeySheetError> {
+        let key = self.key(day).ok_or_else(|| KeySheetError::InvalidDay(format!("{:02}", day)))?;
+        let rings: Vec<String> = key.rings.iter().map(|ring| format!("{:02}", ring)).collect();
+        let plugs: Vec<String> = key.plugs.iter().map(|(a, b)| format!("{}{}", a, b)).collect();
+        let machine_key = format!("{} {} {} {} {} {}", self.model, self.reflector,
+                                  key.rotors.join("-"), rings.join("-"), key.grundstellung,
+                                  plugs.join(" "));
+        machine_key.parse::<Enigma>().map_err(KeySheetError::Enigma)
+    }
+}
+
+/// Number of days in a month of the Gregorian calendar
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+pub enum KeySheetError {
+    InvalidMonth(String),
+    InvalidDay(String),
+    InvalidSheet(String),
+    Enigma(EnigmaError),
+}
+
+impl fmt::Display for KeySheetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeySheetError::InvalidMonth(s) => { write!(f, "Invalid Month: {}", s) }
+            KeySheetError::InvalidDay(s) => { write!(f, "Invalid Day: {}", s) }
+            KeySheetError::InvalidSheet(s) => { write!(f, "Invalid Key Sheet: {}", s) }
+            KeySheetError::Enigma(err) => { write!(f, "{}", err) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_key_sheet {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let sheet = KeySheet::generate(Model::EnigmaI, 1940, 2, 7).ok().unwrap();
+        assert_eq!(29, sheet.days().len());
+        assert_eq!(sheet, KeySheet::generate(Model::EnigmaI, 1940, 2, 7).ok().unwrap());
+        assert_ne!(sheet, KeySheet::generate(Model::EnigmaI, 1940, 2, 8).ok().unwrap());
+        let orders: HashSet<Vec<String>> = sheet.days().iter().map(|key| key.rotors.clone()).collect();
+        assert_eq!(29, orders.len());
+        for key in sheet.days() {
+            let rotors: HashSet<&String> = key.rotors.iter().collect();
+            assert_eq!(3, rotors.len());
+            let letters: HashSet<char> = key.plugs.iter().flat_map(|(a, b)| [*a, *b]).collect();
+            assert_eq!(20, letters.len());
+            assert_eq!(Some(key.day), sheet.day_for_kenngruppe(&key.kenngruppen[2]));
+        }
+        let key = sheet.key(12).unwrap();
+        let machine = sheet.machine(12).ok().unwrap();
+        assert_eq!(key.rotors, machine.rotor_names());
+        assert_eq!(key.grundstellung, machine.positions());
+        assert!(sheet.machine(30).is_err());
+        assert!(KeySheet::generate(Model::EnigmaI, 1940, 13, 7).is_err());
+    }
+
+    #[test]
+    fn test_models() {
+        let m4 = KeySheet::generate(Model::M4, 1942, 3, 1).ok().unwrap();
+        assert!(m4.days().iter().all(|key| ["Beta", "Gamma"].contains(&key.rotors[0].as_str())));
+        assert!(m4.machine(31).is_ok());
+        let k = KeySheet::generate(Model::K, 1939, 6, 1).ok().unwrap();
+        assert!(k.days().iter().all(|key| key.plugs.is_empty()));
+        assert!(k.machine(30).is_ok());
+    }
+
+    #[test]
+    fn test_parse() {
+        let sheet = KeySheet::generate(Model::EnigmaI, 1941, 5, 99).ok().unwrap();
+        let printed = sheet.to_string();
+        assert!(printed.starts_with("Enigma EnigmaI Schlüsselblatt 1941-05, Umkehrwalze B, Seed 99\n\
+            Datum | Walzenlage"));
+        assert!(printed.lines().nth(2).unwrap().starts_with("31    | "));
+        assert_eq!(sheet, printed.parse::<KeySheet>().ok().unwrap());
+        let k = KeySheet::generate(Model::K, 1939, 6, 1).ok().unwrap();
+        assert_eq!(k, k.to_string().parse::<KeySheet>().ok().unwrap());
+        let error = |s: String| s.parse::<KeySheet>().err().unwrap().to_string();
+        let mut bad = sheet.clone();
+        bad.days[3].rotors[1] = "IX".to_string();
+        assert_eq!("Invalid Rotor: IX", error(bad.to_string()));
+        assert!(error(printed.replace("Seed 99", "Seed x")).starts_with("Invalid Key Sheet"));
+    }
+}
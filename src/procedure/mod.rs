@@ -0,0 +1,3 @@
+pub mod key_sheet;
+pub mod indicator;
+pub mod formatting;
@@ -0,0 +1,61 @@
+/// Small deterministic pseudo random number generator (SplitMix64), so that anything
+/// generated from a seed, like a key sheet, can be generated again from the same seed
+#[derive(Clone, Debug)]
+pub(crate) struct Random {
+    state: u64,
+}
+
+impl Random {
+    /// Create a generator from a seed
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+        }
+    }
+
+    /// Next 64 random bits
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Random number in 0..n, n must be greater than 0
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Random lowercase letter
+    pub fn letter(&mut self) -> char {
+        (self.below(26) as u8 + 97u8) as char
+    }
+
+    /// Shuffle a slice in place (Fisher-Yates)
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_random {
+    use super::*;
+
+    #[test]
+    fn test_deterministic() {
+        let mut first = Random::new(42);
+        let mut second = Random::new(42);
+        let mut other = Random::new(43);
+        let values: Vec<u64> = (0..8).map(|_| first.next_u64()).collect();
+        assert!(values.iter().all(|v| *v == second.next_u64()));
+        assert!(values.iter().any(|v| *v != other.next_u64()));
+        let mut items: Vec<u8> = (0..26).collect();
+        first.shuffle(&mut items);
+        items.sort();
+        assert!(items.into_iter().eq(0..26));
+        assert!((0..100).all(|_| first.below(7) < 7 && first.letter().is_ascii_lowercase()));
+    }
+}